@@ -0,0 +1,320 @@
+use crate::{
+    bones::Notation,
+    render::message::{ConwayMessage, PresetMessage},
+};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use strum::IntoEnumIterator;
+
+use ConwayMessage::*;
+use PresetMessage::*;
+
+/*
+    Every operation is written in terms of a few primitive ones,
+    simplified using the identities between those,
+    and then written back out using as few operations as possible.
+*/
+
+/// Operations which are made of other operations,
+/// written in notation order so that the last one is applied first
+const COMPOSITES: &[(ConwayMessage, &[ConwayMessage])] = &[
+    (Join, &[Dual, Ambo]),
+    (Kis, &[Dual, Truncate, Dual]),
+    (Needle, &[Dual, Truncate]),
+    (Zip, &[Truncate, Dual]),
+    (Expand, &[Ambo, Ambo]),
+    (Ortho, &[Dual, Ambo, Ambo]),
+    (Bevel, &[Truncate, Ambo]),
+    (Meta, &[Dual, Truncate, Ambo]),
+    (Subdivide, &[Dual, Chamfer, Dual]),
+];
+
+/// Identities between primitive operations, each of which makes the notation simpler
+const REDUCTIONS: &[(&[ConwayMessage], &[ConwayMessage])] = &[
+    (&[Dual, Dual], &[]),
+    (&[Reflect, Reflect], &[]),
+    // Ambo doesn't care which of a dual pair it is applied to,
+    // while gyro gives mirror images of each other.
+    // `s = dg` too, once snub stops being an alternated expand.
+    (&[Ambo, Dual], &[Ambo]),
+    (&[Gyro, Dual], &[Reflect, Gyro]),
+    // Duals move towards the seed, where they might disappear
+    (&[Dual, Propeller], &[Propeller, Dual]),
+];
+
+/// Operations applied to seeds which produce other seeds
+const SEED_IDENTITIES: &[(ConwayMessage, PresetMessage, PresetMessage)] = &[
+    (Dual, Prism(4), Octahedron),
+    (Dual, Octahedron, Prism(4)),
+    (Dual, Dodecahedron, Icosahedron),
+    (Dual, Icosahedron, Dodecahedron),
+    (Ambo, Pyramid(3), Octahedron),
+    (Gyro, Pyramid(3), Dodecahedron),
+];
+
+impl ConwayMessage {
+    /// Whether the operation has a handedness, so that it can't swap places with a reflection
+    pub fn chiral(&self) -> bool {
+        match self {
+            Gyro | Snub | Propeller | Whirl => true,
+            GoldbergCoxeter(m, n) => m != n && *m != 0 && *n != 0,
+            _ => false,
+        }
+    }
+
+    /// The primitive operations this one is made of
+    fn primitives(&self) -> Vec<ConwayMessage> {
+        COMPOSITES
+            .iter()
+            .find(|(composite, _)| composite == self)
+            .map(|(_, primitives)| primitives.to_vec())
+            .unwrap_or_else(|| vec![self.clone()])
+    }
+}
+
+impl PresetMessage {
+    /// The seed which `conway` turns this one into, if it is a seed at all
+    fn transform(&self, conway: &ConwayMessage) -> Option<PresetMessage> {
+        match (conway, self) {
+            // Pyramids are self-dual
+            (Dual, Pyramid(_)) => Some(self.clone()),
+            _ => SEED_IDENTITIES
+                .iter()
+                .find(|(c, from, _)| c == conway && from == self)
+                .map(|(_, _, to)| to.clone()),
+        }
+    }
+}
+
+/// How the next operation written lines up with the primitive operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Owed {
+    /// It starts where the last one ended
+    Nothing,
+    /// It may start with a dual which the last operation absorbs
+    Absorbed,
+    /// It must start with a dual which cancels one the last operation ended with
+    Dual,
+}
+
+impl Notation {
+    /// Shortest notation for the same polyhedron that the known identities can reach.
+    /// Ties are broken alphabetically, so that equivalent notations agree.
+    pub fn simplify(&self) -> Notation {
+        let operations = Self::reduce(
+            self.operations
+                .iter()
+                .flat_map(|c| c.primitives())
+                .collect(),
+        );
+        // The triangular antiprism is always written as an octahedron
+        let seed = match self.seed {
+            AntiPrism(3) => Octahedron,
+            _ => self.seed.clone(),
+        };
+
+        // Move operations in and out of the seed
+        let mut seen: HashSet<(Vec<ConwayMessage>, PresetMessage)> = Default::default();
+        let mut queue = vec![(operations, seed)];
+        let mut candidates = Vec::new();
+        while let Some((operations, seed)) = queue.pop() {
+            if !seen.insert((operations.clone(), seed.clone())) {
+                continue;
+            }
+            if let Some(last) = operations.last() {
+                if let Some(transformed) = seed.transform(last) {
+                    let operations = operations[..operations.len() - 1].to_vec();
+                    queue.push((Self::reduce(operations), transformed));
+                }
+            }
+            if let Some(dual) = seed.transform(&Dual) {
+                queue.push((Self::reduce([&operations[..], &[Dual]].concat()), dual));
+            }
+            candidates.push((operations, seed));
+        }
+
+        candidates
+            .into_iter()
+            .filter_map(|(primitives, seed)| {
+                let operations = Self::tile(&primitives)?;
+                Some(Notation { operations, seed })
+            })
+            .min_by_key(|notation| (notation.operations.len(), notation.to_string()))
+            .unwrap_or_else(|| self.clone())
+    }
+
+    /// Apply the reductions until none are left
+    fn reduce(mut operations: Vec<ConwayMessage>) -> Vec<ConwayMessage> {
+        // Reflections move towards the seed past anything achiral
+        let reflections = ConwayMessage::iter()
+            .filter(|c| !c.chiral() && *c != Reflect)
+            .map(|c| (vec![Reflect, c.clone()], vec![c, Reflect]));
+        let reductions = REDUCTIONS
+            .iter()
+            .map(|(from, to)| (from.to_vec(), to.to_vec()))
+            .chain(reflections)
+            .collect::<Vec<_>>();
+
+        'reduce: loop {
+            for (from, to) in reductions.iter() {
+                if let Some(i) = operations.windows(from.len()).position(|w| w == from) {
+                    operations.splice(i..i + from.len(), to.iter().cloned());
+                    continue 'reduce;
+                }
+            }
+            // Every seed is its own mirror image
+            if operations.last() == Some(&Reflect) {
+                operations.pop();
+                continue;
+            }
+            return operations;
+        }
+    }
+
+    /// Write primitive operations using as few operations as possible
+    fn tile(primitives: &[ConwayMessage]) -> Option<Vec<ConwayMessage>> {
+        let tokens = ConwayMessage::iter()
+            .filter(|c| !matches!(c, GoldbergCoxeter(..)))
+            .chain(
+                primitives
+                    .iter()
+                    .filter(|c| matches!(c, GoldbergCoxeter(..)))
+                    .cloned(),
+            )
+            .map(|c| (c.primitives(), c))
+            .collect::<Vec<_>>();
+
+        // Best way to write everything from each position onwards
+        type Tiling = (usize, String, Vec<ConwayMessage>);
+        let mut best: HashMap<(usize, Owed), Tiling> = Default::default();
+        let n = primitives.len();
+        best.insert((n, Owed::Nothing), Default::default());
+        best.insert((n, Owed::Absorbed), Default::default());
+        for i in (0..n).rev() {
+            for owed in [Owed::Nothing, Owed::Absorbed, Owed::Dual] {
+                let mut options = Vec::new();
+                for (expansion, token) in tokens.iter() {
+                    let skip = expansion.first() == Some(&Dual) && owed != Owed::Nothing;
+                    if owed == Owed::Dual && !skip {
+                        continue;
+                    }
+                    let expansion = &expansion[skip as usize..];
+                    // Either the whole expansion is written,
+                    // or its final dual cancels with the start of the next one
+                    let mut ways = vec![(expansion, Owed::Nothing)];
+                    if matches!(expansion.last(), Some(Ambo | Gyro)) {
+                        ways[0].1 = Owed::Absorbed;
+                    }
+                    if let Some((Dual, rest)) = expansion.split_last() {
+                        ways.push((rest, Owed::Dual));
+                    }
+                    for (expansion, next) in ways {
+                        if expansion.is_empty() || !primitives[i..].starts_with(expansion) {
+                            continue;
+                        }
+                        if let Some((count, notation, operations)) =
+                            best.get(&(i + expansion.len(), next))
+                        {
+                            options.push((
+                                count + 1,
+                                token.notation() + notation,
+                                [std::slice::from_ref(token), &operations[..]].concat(),
+                            ));
+                        }
+                    }
+                }
+                if let Some(tiling) = options
+                    .into_iter()
+                    .min_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)))
+                {
+                    best.insert((i, owed), tiling);
+                }
+            }
+        }
+        best.remove(&(0, Owed::Nothing))
+            .map(|(_, _, operations)| operations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{COMPOSITES, REDUCTIONS, SEED_IDENTITIES};
+    use crate::{
+        bones::{Fingerprint, Notation},
+        render::message::{ConwayMessage, PresetMessage},
+    };
+    use test_case::test_case;
+
+    #[test_case("ddC", "C"; "dd")]
+    #[test_case("aaT", "aC"; "aa")]
+    #[test_case("adD", "aD"; "ad")]
+    #[test_case("daC", "jC"; "da")]
+    #[test_case("djC", "aC"; "dj")]
+    #[test_case("ajC", "eC"; "aj")]
+    #[test_case("dtC", "kO"; "dt")]
+    #[test_case("dkD", "tI"; "dk")]
+    #[test_case("taC", "bC"; "ta")]
+    #[test_case("kjD", "mD"; "kj")]
+    #[test_case("jjC", "oC"; "jj")]
+    #[test_case("deC", "oC"; "de")]
+    #[test_case("dbD", "mD"; "db")]
+    #[test_case("tadC", "bC"; "tad")]
+    #[test_case("gdC", "gO"; "gd")]
+    #[test_case("pgdD", "pgI"; "mirror")]
+    #[test_case("dcdD", "uD"; "dcd")]
+    #[test_case("dpD", "pI"; "dp")]
+    #[test_case("dO", "C"; "dual octahedron")]
+    #[test_case("dT", "T"; "dual tetrahedron")]
+    #[test_case("dY5", "Y5"; "dual pyramid")]
+    #[test_case("aA3", "aC"; "antiprism")]
+    #[test_case("kdtdC", "kkC"; "kdtd")]
+    #[test_case("gT", "D"; "gyro tetrahedron")]
+    #[test_case("rrgC", "gC"; "rr")]
+    #[test_case("rpC", "rpC"; "chiral")]
+    #[test_case("rgC", "gO"; "reflected gyro")]
+    #[test_case("rkC", "kC"; "achiral")]
+    #[test_case("rgdC", "gC"; "rgd")]
+    #[test_case("tkD", "tkD"; "already simple")]
+    #[test_case("GC(2,1)dD", "GC(2,1)I"; "goldberg coxeter")]
+    fn simplify(notation: &str, simplified: &str) {
+        let notation: Notation = notation.parse().unwrap();
+        assert_eq!(notation.simplify().to_string(), simplified);
+    }
+
+    /// Both sides of every identity build the same polyhedron
+    #[test]
+    fn identities() {
+        let build = |operations: &[ConwayMessage], seed: &PresetMessage| -> Fingerprint {
+            Notation {
+                operations: operations.to_vec(),
+                seed: seed.clone(),
+            }
+            .structure()
+            .unwrap()
+            .fingerprint()
+            .clone()
+        };
+        let seeds = [
+            PresetMessage::Pyramid(3),
+            PresetMessage::Prism(4),
+            PresetMessage::Dodecahedron,
+            PresetMessage::Prism(5),
+            PresetMessage::AntiPrism(4),
+        ];
+        for seed in seeds.iter() {
+            for (composite, primitives) in COMPOSITES {
+                let a = build(std::slice::from_ref(composite), seed);
+                assert_eq!(a, build(primitives, seed), "{composite:?} on {seed:?}");
+            }
+            for (a, b) in REDUCTIONS {
+                assert_eq!(build(a, seed), build(b, seed), "{a:?} on {seed:?}");
+            }
+        }
+        for (conway, a, b) in SEED_IDENTITIES {
+            assert_eq!(
+                build(std::slice::from_ref(conway), a),
+                build(&[], b),
+                "{conway:?} on {a:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::bones::{PolyGraph, VertexId};
+use rustc_hash::FxHashMap as HashMap;
+use std::ops::Range;
+use ultraviolet::Vec3;
+
+/// Widest a group of vertices can look from a vertex and still push on it as one
+const THETA: f32 = 0.8;
+/// Most vertices in a node before it is split
+const LEAF_VERTICES: usize = 4;
+/// Deepest nodes are split, since vertices can sit on top of each other
+const MAX_DEPTH: usize = 16;
+/// Strength of the push between vertices, relative to the pull of the springs
+const REPULSION: f32 = 0.5;
+/// Pull of the springs between opposite vertices, relative to those between near ones
+const FAR_STIFFNESS: f32 = 0.1;
+const SPEED_DAMPENING: f32 = 0.5;
+
+/// Splits space into cubes, then each cube into eight, until each holds a few points.
+/// Distant cubes can then be treated as one point at their centroid.
+struct Octree {
+    nodes: Vec<Node>,
+    /// Points in the order the nodes hold them
+    order: Vec<usize>,
+}
+
+struct Node {
+    centroid: Vec3,
+    count: f32,
+    width: f32,
+    /// Nodes for the occupied eighths of this one, none for a leaf
+    children: Vec<usize>,
+    /// Range of `order` that this node holds
+    points: Range<usize>,
+}
+
+impl Octree {
+    fn new(points: &[Vec3]) -> Self {
+        let mut tree = Octree {
+            nodes: Vec::new(),
+            order: (0..points.len()).collect(),
+        };
+        if !points.is_empty() {
+            let (min, max) = points.iter().fold(
+                (Vec3::broadcast(f32::MAX), Vec3::broadcast(f32::MIN)),
+                |(min, max), &p| (min.min_by_component(p), max.max_by_component(p)),
+            );
+            let width = (max - min).component_max();
+            tree.split(points, 0..points.len(), (min + max) / 2.0, width, 0);
+        }
+        tree
+    }
+
+    fn split(
+        &mut self,
+        points: &[Vec3],
+        range: Range<usize>,
+        center: Vec3,
+        width: f32,
+        depth: usize,
+    ) -> usize {
+        let index = self.nodes.len();
+        let sum = self.order[range.clone()]
+            .iter()
+            .fold(Vec3::zero(), |sum, &i| sum + points[i]);
+        let count = range.len() as f32;
+        self.nodes.push(Node {
+            centroid: sum / count,
+            count,
+            width,
+            children: Vec::new(),
+            points: range.clone(),
+        });
+        if range.len() <= LEAF_VERTICES || depth == MAX_DEPTH {
+            return index;
+        }
+
+        let octant = |p: Vec3| {
+            (p.x > center.x) as usize
+                | ((p.y > center.y) as usize) << 1
+                | ((p.z > center.z) as usize) << 2
+        };
+        self.order[range.clone()].sort_by_key(|&i| octant(points[i]));
+        let mut start = range.start;
+        for o in 0..8 {
+            let end = start
+                + self.order[start..range.end]
+                    .iter()
+                    .take_while(|&&i| octant(points[i]) == o)
+                    .count();
+            if end > start {
+                let side = |bit: usize| if o & bit == 0 { -0.25 } else { 0.25 };
+                let offset = Vec3::new(side(1), side(2), side(4)) * width;
+                let child = self.split(points, start..end, center + offset, width / 2.0, depth + 1);
+                self.nodes[index].children.push(child);
+            }
+            start = end;
+        }
+        index
+    }
+
+    /// Sum of pushes on `p` from every point, falling off with distance
+    fn push(&self, points: &[Vec3], p: Vec3) -> Vec3 {
+        let away = |from: Vec3, weight: f32| {
+            let diff = p - from;
+            let distance = diff.mag_sq();
+            // Points on top of each other have no direction to push in
+            if distance > f32::EPSILON {
+                diff * weight / distance
+            } else {
+                Vec3::zero()
+            }
+        };
+        let mut push = Vec3::zero();
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let Some(node) = self.nodes.get(n) else {
+                continue;
+            };
+            if node.children.is_empty() {
+                for &i in self.order[node.points.clone()].iter() {
+                    push += away(points[i], 1.0);
+                }
+            } else if node.width * node.width < THETA * THETA * (p - node.centroid).mag_sq() {
+                push += away(node.centroid, node.count);
+            } else {
+                stack.extend(node.children.iter());
+            }
+        }
+        push
+    }
+}
+
+impl PolyGraph {
+    /// Springs between vertices up to two edges apart and between opposite ones, while every vertex pushes the rest away.
+    /// Far away vertices push together through an octree, so each frame takes `O(V log V)`.
+    pub fn apply_barnes_hut_forces(&mut self, speed: f32, second: f32) {
+        // The longest springs reach across the polyhedron
+        let diameter = self
+            .springs
+            .values()
+            .copied()
+            .max()
+            .unwrap_or_default()
+            .max(1) as f32;
+        // Length of a spring along one edge in the spring layout
+        let edge_length = self.edge_length * 2.0 / diameter;
+
+        let vertices = self.vertices.iter().copied().collect::<Vec<_>>();
+        let points = vertices
+            .iter()
+            .map(|v| self.positions[v])
+            .collect::<Vec<_>>();
+        let tree = Octree::new(&points);
+        let mut forces: HashMap<VertexId, Vec3> = vertices
+            .iter()
+            .zip(points.iter())
+            .map(|(&v, &p)| (v, tree.push(&points, p) * edge_length.powi(2) * REPULSION))
+            .collect();
+
+        let near = self.springs.iter().filter(|(_, &d)| d <= 2);
+        for (e, &d) in near.chain(self.far_springs.iter()) {
+            let diff = self.positions[&e.v()] - self.positions[&e.u()];
+            // Opposite vertices only hold the polyhedron open, so pull more gently
+            let stiffness = if d <= 2 { 1.0 } else { FAR_STIFFNESS };
+            let f = diff * (edge_length * d as f32 - diff.mag()) * stiffness;
+            *forces.entry(e.v()).or_default() += f;
+            *forces.entry(e.u()).or_default() -= f;
+        }
+
+        for (v, force) in forces {
+            let velocity = (self.speeds[&v] + force / speed * second) * SPEED_DAMPENING;
+            self.speeds.insert(v, velocity);
+            *self.positions.entry(v).or_default() += velocity;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Octree;
+    use crate::{bones::PolyGraph, render::message::LayoutMessage};
+    use test_case::test_case;
+    use ultraviolet::Vec3;
+
+    /// Far field approximations stay close to summing every push directly
+    #[test]
+    fn approximation() {
+        let graph: PolyGraph = "tkD".parse().unwrap();
+        let points = graph.positions.values().copied().collect::<Vec<_>>();
+        let tree = Octree::new(&points);
+        for &p in points.iter() {
+            let exact = points
+                .iter()
+                .filter(|&&q| q != p)
+                .fold(Vec3::zero(), |sum, &q| sum + (p - q) / (p - q).mag_sq());
+            let approximate = tree.push(&points, p);
+            assert!((exact - approximate).mag() < 0.05 * exact.mag().max(1.0));
+        }
+    }
+
+    /// Settles into an even layout the same size as the spring one
+    #[test_case("tD"; "tD")]
+    #[test_case("kkD"; "kkD")]
+    #[test_case("gC"; "gC")]
+    fn layout(notation: &str) {
+        let mut graph: PolyGraph = notation.parse().unwrap();
+        for _ in 0..1000 {
+            graph
+                .update(&LayoutMessage::BarnesHut, 10.0, 1.0 / 60.0)
+                .unwrap();
+        }
+        let lengths = graph
+            .edges
+            .iter()
+            .map(|e| (graph.positions[&e.v()] - graph.positions[&e.u()]).mag())
+            .collect::<Vec<_>>();
+        let shortest = lengths.iter().copied().fold(f32::MAX, f32::min);
+        let longest = lengths.iter().copied().fold(0.0, f32::max);
+        assert!(shortest > 0.0 && longest < 4.0 * shortest);
+        let radius = graph
+            .positions
+            .values()
+            .map(|p| p.mag())
+            .fold(0.0, f32::max);
+        assert!((radius - 1.0).abs() < 0.1);
+    }
+}
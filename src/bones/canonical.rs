@@ -0,0 +1,164 @@
+use crate::bones::PolyGraph;
+use rustc_hash::FxHashMap as HashMap;
+use ultraviolet::Vec3;
+
+/// Largest vertex movement per iteration at which canonicalization is finished
+pub const CANONICAL_TOLERANCE: f32 = 1e-6;
+
+// Canonical form
+impl PolyGraph {
+    /// Iterate towards the canonical form of the polyhedron,
+    /// in which every face is planar, every edge is tangent to the unit sphere
+    /// and the points of tangency are centered on the origin.
+    /// Returns the number of iterations taken, or `None` if no vertex
+    /// had stopped moving by more than `tolerance` after `max_iterations`.
+    pub fn canonicalize(&mut self, tolerance: f32, max_iterations: usize) -> Option<usize> {
+        (1..=max_iterations).find(|_| self.canonical_step() < tolerance)
+    }
+
+    /// Move every vertex once towards canonical form,
+    /// returning the largest distance any vertex moved
+    pub fn canonical_step(&mut self) -> f32 {
+        let old = self.positions.clone();
+        self.tangentify();
+        self.recenter();
+        self.planarize();
+        self.positions
+            .iter()
+            .map(|(v, p)| (*p - old[v]).mag())
+            .fold(0.0, f32::max)
+    }
+
+    /// Point on the line through an edge which is closest to the origin
+    fn tangent_point(&self, v: usize, u: usize) -> Vec3 {
+        let (v, u) = (self.positions[&v], self.positions[&u]);
+        let d = u - v;
+        let length = d.mag_sq();
+        if length == 0.0 {
+            v
+        } else {
+            v - d * (d.dot(v) / length)
+        }
+    }
+
+    /// Push both ends of each edge so that it lies closer to tangent to the unit sphere
+    fn tangentify(&mut self) {
+        let mut adjustments: HashMap<usize, (Vec3, f32)> = Default::default();
+        for e in self.edges.iter() {
+            let t = self.tangent_point(e.v(), e.u());
+            let c = t * (1.0 - t.mag());
+            for v in [e.v(), e.u()] {
+                let adjustment = adjustments.entry(v).or_default();
+                adjustment.0 += c;
+                adjustment.1 += 1.0;
+            }
+        }
+        self.adjust(adjustments);
+    }
+
+    /// Move the points of tangency so that they are centered on the origin
+    fn recenter(&mut self) {
+        if self.edges.is_empty() {
+            return;
+        }
+        let shift = self
+            .edges
+            .iter()
+            .map(|e| self.tangent_point(e.v(), e.u()))
+            .fold(Vec3::zero(), |a, b| a + b)
+            / self.edges.len() as f32;
+        for p in self.positions.values_mut() {
+            *p -= shift;
+        }
+    }
+
+    /// Project the vertices of each face towards the plane through its centroid
+    fn planarize(&mut self) {
+        let mut adjustments: HashMap<usize, (Vec3, f32)> = Default::default();
+        for i in 0..self.cycles.len() {
+            let positions = self.face_positions(i);
+            let centroid = self.face_centroid(i);
+            // Newell's method averages the normal over every corner
+            let normal = (0..positions.len())
+                .map(|j| positions[j].cross(positions[(j + 1) % positions.len()]))
+                .fold(Vec3::zero(), |a, b| a + b);
+            if normal.mag_sq() == 0.0 {
+                continue;
+            }
+            let normal = normal.normalized();
+            for (&v, p) in self.cycles[i].iter().zip(positions) {
+                let adjustment = adjustments.entry(v).or_default();
+                adjustment.0 += normal * normal.dot(centroid - p);
+                adjustment.1 += 1.0;
+            }
+        }
+        self.adjust(adjustments);
+    }
+
+    /// Move each vertex by the average of the adjustments asked of it
+    fn adjust(&mut self, adjustments: HashMap<usize, (Vec3, f32)>) {
+        for (v, (sum, count)) in adjustments {
+            *self.positions.entry(v).or_default() += sum / count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bones::{settled, CANONICAL_TOLERANCE};
+    use test_case::test_case;
+    use ultraviolet::Vec3;
+
+    const ITERATIONS: usize = 5000;
+
+    #[test_case("T"; "T")]
+    #[test_case("C"; "C")]
+    #[test_case("O"; "O")]
+    #[test_case("D"; "D")]
+    #[test_case("I"; "I")]
+    #[test_case("tI"; "tI")]
+    #[test_case("aD"; "aD")]
+    #[test_case("gC"; "gC")]
+    #[test_case("P5"; "P5")]
+    #[test_case("A7"; "A7")]
+    #[test_case("Y6"; "Y6")]
+    #[test_case("sD"; "sD")]
+    #[test_case("GC(2,1)I"; "GC(2,1)I")]
+    fn canonicalize(notation: &str) {
+        let mut graph = settled(notation);
+        assert!(graph
+            .canonicalize(CANONICAL_TOLERANCE, ITERATIONS)
+            .is_some());
+
+        // Edges are tangent to the unit sphere
+        let mut center = Vec3::zero();
+        for e in graph.edges.iter() {
+            let t = graph.tangent_point(e.v(), e.u());
+            assert!((t.mag() - 1.0).abs() < 1e-3, "{}", t.mag());
+            center += t;
+        }
+        // Around the origin
+        assert!((center / graph.edges.len() as f32).mag() < 1e-3);
+        // And faces are planar
+        for i in 0..graph.cycles.len() {
+            let positions = graph.face_positions(i);
+            let normal = (positions[1] - positions[0])
+                .cross(positions[2] - positions[0])
+                .normalized();
+            for p in positions.iter() {
+                assert!(normal.dot(*p - positions[0]).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn cube() {
+        let mut graph = settled("C");
+        graph.canonicalize(CANONICAL_TOLERANCE, ITERATIONS);
+        // Edges of a cube tangent to the unit sphere have length √2
+        for e in graph.edges.iter() {
+            let length = (graph.positions[&e.v()] - graph.positions[&e.u()]).mag();
+            assert!((length - 2f32.sqrt()).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::bones::PolyGraph;
+use rustc_hash::FxHashMap as HashMap;
+use std::collections::BTreeSet;
+
+/// Most faces the fewest colors are searched for exhaustively,
+/// any more and the heuristic's coloring is used as it is
+const EXACT_FACES: usize = 128;
+/// Most faces colored in, across all the attempts of the exhaustive search
+const EXACT_STEPS: usize = 200_000;
+
+/// Faces which share an edge, by index into `cycles`
+struct Dual {
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl Dual {
+    fn new(graph: &PolyGraph) -> Self {
+        let mut sides: HashMap<_, Vec<usize>> = HashMap::default();
+        for (i, cycle) in graph.cycles.iter().enumerate() {
+            for e in cycle.edges() {
+                sides.entry(e).or_default().push(i);
+            }
+        }
+        let mut neighbours = vec![Vec::new(); graph.cycles.len()];
+        for faces in sides.into_values() {
+            for &i in faces.iter() {
+                neighbours[i].extend(faces.iter().copied().filter(|&j| j != i));
+            }
+        }
+        for n in neighbours.iter_mut() {
+            n.sort();
+            n.dedup();
+        }
+        Dual { neighbours }
+    }
+
+    /// DSatur: color the face touching the most different colors next, with a color none of
+    /// its neighbours have. That's the lowest one, or with a `spread` the least used below it
+    /// when there is one.
+    fn saturation(&self, spread: Option<usize>) -> Vec<usize> {
+        let n = self.neighbours.len();
+        let mut colors = vec![usize::MAX; n];
+        let mut used: Vec<usize> = vec![0; spread.unwrap_or_default()];
+        let mut seen: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); n];
+        // Most saturated, then most neighbours, then the first face
+        let key = |i: usize, seen: &[BTreeSet<usize>]| {
+            (
+                usize::MAX - seen[i].len(),
+                usize::MAX - self.neighbours[i].len(),
+                i,
+            )
+        };
+        let mut queue = (0..n).map(|i| key(i, &seen)).collect::<BTreeSet<_>>();
+        while let Some((_, _, i)) = queue.pop_first() {
+            let color = (0..used.len())
+                .filter(|c| !seen[i].contains(c))
+                .min_by_key(|&c| (used[c], c))
+                .unwrap_or_else(|| (0..).find(|c| !seen[i].contains(c)).unwrap_or_default());
+            if let Some(count) = used.get_mut(color) {
+                *count += 1;
+            }
+            colors[i] = color;
+            for &j in self.neighbours[i].iter() {
+                if colors[j] == usize::MAX && !seen[j].contains(&color) {
+                    queue.remove(&key(j, &seen));
+                    seen[j].insert(color);
+                    queue.insert(key(j, &seen));
+                }
+            }
+        }
+        colors
+    }
+
+    /// Backtracking search for a coloring with `k` colors, giving up after `steps` runs out
+    fn search(&self, k: usize, steps: &mut usize) -> Option<Vec<usize>> {
+        let mut colors = vec![usize::MAX; self.neighbours.len()];
+        self.extend(k, &mut colors, steps).then_some(colors)
+    }
+
+    /// Color the most saturated face left in every way that fits, then the rest after it
+    fn extend(&self, k: usize, colors: &mut [usize], steps: &mut usize) -> bool {
+        let saturation = |i: usize| {
+            let mut seen = self.neighbours[i]
+                .iter()
+                .map(|&j| colors[j])
+                .filter(|&c| c != usize::MAX)
+                .collect::<Vec<_>>();
+            seen.sort();
+            seen.dedup();
+            (seen.len(), self.neighbours[i].len())
+        };
+        let Some(i) = (0..colors.len())
+            .filter(|&i| colors[i] == usize::MAX)
+            .max_by_key(|&i| (saturation(i), usize::MAX - i))
+        else {
+            return true;
+        };
+        if *steps == 0 {
+            return false;
+        }
+        *steps -= 1;
+        for c in 0..k {
+            if self.neighbours[i].iter().any(|&j| colors[j] == c) {
+                continue;
+            }
+            colors[i] = c;
+            if self.extend(k, colors, steps) {
+                return true;
+            }
+            colors[i] = usize::MAX;
+        }
+        false
+    }
+}
+
+impl PolyGraph {
+    /// Color every face so that no two faces sharing an edge match, numbering the colors from zero.
+    /// Uses `colors` colors if it can, or else the fewest that could be found,
+    /// which is at most four for small polyhedra and usually for large ones.
+    pub fn face_coloring(&self, colors: usize) -> Vec<usize> {
+        let dual = Dual::new(self);
+        let greedy = dual.saturation(None);
+        let mut steps = if self.cycles.len() <= EXACT_FACES {
+            EXACT_STEPS
+        } else {
+            0
+        };
+        // Look for fewer and fewer colors than the heuristic found, until there are none
+        let mut fewest = greedy;
+        while let Some(coloring) = count(&fewest)
+            .checked_sub(1)
+            .and_then(|k| dual.search(k, &mut steps))
+        {
+            fewest = coloring;
+        }
+        // Then spread the faces over any spare colors, if that still works out
+        if colors > count(&fewest) {
+            let spread = dual.saturation(Some(colors));
+            if count(&spread) <= colors {
+                return spread;
+            }
+        }
+        fewest
+    }
+}
+
+/// Number of colors used by a coloring
+fn count(coloring: &[usize]) -> usize {
+    coloring.iter().map(|&c| c + 1).max().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{count, Dual};
+    use crate::bones::PolyGraph;
+    use test_case::test_case;
+
+    #[test_case("T", 4; "T")]
+    #[test_case("C", 3; "C")]
+    #[test_case("O", 2; "O")]
+    #[test_case("D", 4; "D")]
+    #[test_case("I", 3; "I")]
+    #[test_case("P5", 4; "P5")]
+    #[test_case("P6", 3; "P6")]
+    #[test_case("tI", 4; "tI")]
+    #[test_case("aC", 2; "aC")]
+    #[test_case("gC", 3; "gC")]
+    fn fewest(notation: &str, colors: usize) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        let coloring = graph.face_coloring(1);
+        let dual = Dual::new(&graph);
+        for (i, neighbours) in dual.neighbours.iter().enumerate() {
+            assert!(neighbours.iter().all(|&j| coloring[i] != coloring[j]));
+        }
+        assert_eq!(count(&coloring), colors);
+    }
+
+    /// Spare colors all get used, and big polyhedra still fit in four
+    #[test_case("C", 5, 5; "spread")]
+    #[test_case("kttkD", 1, 3; "large")]
+    fn colors(notation: &str, requested: usize, colors: usize) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        let coloring = graph.face_coloring(requested);
+        let dual = Dual::new(&graph);
+        for (i, neighbours) in dual.neighbours.iter().enumerate() {
+            assert!(neighbours.iter().all(|&j| coloring[i] != coloring[j]));
+        }
+        assert_eq!(count(&coloring), colors);
+    }
+}
@@ -1,311 +1,1067 @@
 use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
-use std::collections::VecDeque;
-use ultraviolet::Vec3;
+use ultraviolet::{Lerp, Vec3};
 
-use crate::bones::*;
+use crate::{bones::*, render::message::ConwayMessage};
 
 impl PolyGraph {
-    pub fn contract_edge(&mut self, e: impl Into<Edge>) {
-        let e: Edge = e.into();
-        // Give u all the same connections as v
-        for w in self.connections(e.v()).into_iter() {
-            self.connect((w, e.u()));
+    /// Change the structure and name of the graph without preparing its layout
+    pub fn operate(&mut self, conway: &ConwayMessage) -> Result<(), PolyGraphError> {
+        use ConwayMessage::*;
+        // Bevel's own operations each start a generation
+        if *conway != Bevel {
+            self.begin_generation();
         }
-        // Delete a
-        for f in self.cycles.iter_mut() {
-            f.replace(e.v(), e.u());
+        match conway {
+            Dual => self.dual()?,
+            Join => {
+                let edges = self.kis(None)?;
+                self.release(edges)?;
+            }
+            Ambo => {
+                let edges = self.ambo()?;
+                self.contract_edges(edges)?;
+            }
+            Kis => {
+                self.kis(None)?;
+            }
+            Needle => self.needle()?,
+            Zip => self.zip()?,
+            Truncate => {
+                self.truncate(None)?;
+            }
+            Ortho => self.ortho()?,
+            Expand => {
+                self.expand(false)?;
+            }
+            Chamfer => self.chamfer()?,
+            Subdivide => self.subdivide()?,
+            Gyro => self.gyro()?,
+            Snub => {
+                self.expand(true)?;
+            }
+            Meta => self.meta()?,
+            Reflect => self.reflect(),
+            Loft => self.loft()?,
+            Propeller => self.propeller()?,
+            Whirl => self.whirl()?,
+            Quinto => self.quinto()?,
+            JoinLace => self.join_lace()?,
+            // Named by the operations it is made of, which simplify to `b`
+            Bevel => {
+                self.operate(&Ambo)?;
+                return self.operate(&Truncate);
+            }
+            GoldbergCoxeter(m, n) => self.goldberg_coxeter(*m, *n)?,
         }
+        self.rename(&conway.notation());
+        self.orient_outward();
+        self.sort_faces();
+        Ok(())
+    }
 
-        self.edges = self
-            .edges
-            .clone()
-            .into_iter()
-            .map(|f| {
-                if let Some(w) = f.other(e.v()) {
-                    (e.u(), w).into()
-                } else {
-                    f
-                }
-            })
-            .filter(|e| e.v() != e.u())
-            .collect();
+    /// Prepend an operation to the name, then simplify it
+    pub fn rename(&mut self, c: &str) {
+        let name = format!("{c}{}", self.name);
+        self.name = match name.parse::<Notation>() {
+            Ok(notation) => notation.simplify().to_string(),
+            Err(_) => name,
+        };
+    }
 
-        self.delete(e.v());
+    /// Contract one edge, merging its ends
+    #[cfg(test)]
+    pub fn contract_edge(&mut self, e: impl Into<Edge>) -> Result<(), PolyGraphError> {
+        self.contract_edges([e.into()].into_iter().collect())
     }
 
-    pub fn contract_edges(&mut self, edges: HashSet<Edge>) {
+    /// Contract every edge, following the vertices they merge into.
+    /// Faces which shrink to an edge or a point are gone afterwards.
+    pub fn contract_edges(&mut self, edges: HashSet<Edge>) -> Result<(), PolyGraphError> {
         let mut map = HashMap::<VertexId, VertexId>::default();
-        for e in edges.into_iter() {
-            let u = e.u();
-            let v = e.v();
-            let e = match (map.get(&u), map.get(&v)) {
-                (None, None) => e,
-                (None, Some(v)) => (&u, v).into(),
-                (Some(u), None) => (u, &v).into(),
-                (Some(u), Some(v)) => (u, v).into(),
-            };
+        let follow = |map: &HashMap<VertexId, VertexId>, mut v| {
+            while let Some(&w) = map.get(&v) {
+                v = w;
+            }
+            v
+        };
+        for e in edges.iter() {
+            // Follow the chain of previous contractions to find where each endpoint went
+            let e: Edge = (follow(&map, e.u()), follow(&map, e.v())).into();
             if e.v() != e.u() {
-                self.contract_edge(e);
                 map.insert(e.v(), e.u());
             }
         }
+        let merged = |v| follow(&map, v);
 
-        self.cycles = self
-            .cycles
-            .clone()
-            .into_iter()
-            .filter(|c| c.len() > 2)
-            .collect();
-    }
-
-    pub fn split_vertex(&mut self, v: VertexId) -> HashSet<Edge> {
-        let original_position = self.positions[&v];
-        let mut connections: VecDeque<VertexId> = self.connections(v).into_iter().collect();
-        let mut transformations: HashMap<VertexId, VertexId> = Default::default();
-        let mut new_face = Vec::new();
-
-        // Remove the vertex
-
-        // connect a new node to every existing connection
-        while let Some(u) = connections.pop_front() {
-            // Insert a new node in the same location
-            let new_vertex = self.insert();
-            // Track it in the new face
-            new_face.push(new_vertex);
-            // Update pos
-            self.positions.insert(new_vertex, original_position);
-            // Reform old connection
-            self.connect((u, new_vertex));
-            // track transformation
-            transformations.insert(u, new_vertex);
+        // Faces keep every corner that didn't merge into the one before it
+        let mut cycles = Vec::new();
+        let mut faces = Vec::new();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let mut corners = cycle.iter().map(|&v| merged(v)).collect::<Vec<_>>();
+            corners.dedup();
+            while corners.len() > 1 && corners.first() == corners.last() {
+                corners.pop();
+            }
+            if corners.len() > 2 {
+                cycles.push(Face::new(corners));
+                faces.push(Origin::Face(i));
+            }
         }
+        let half_edges = HalfEdges::from_cycles(&cycles)?;
+        let before = self.snapshot();
+        self.replace(half_edges);
 
-        // track the edges that will compose the new face
-        let mut new_edges = HashSet::default();
-
-        // upate every face
-        for i in 0..self.cycles.len() {
-            // if this face had v in it
-            if let Some(vi) = self.cycles[i].iter().position(|&x| x == v) {
-                // indices before and after v in face
-                let vh = (vi + self.cycles[i].len() - 1) % self.cycles[i].len();
-                let vj = (vi + 1) % self.cycles[i].len();
+        // Vertices that merged together came from whatever they all came from,
+        // or the edges between them or face around them did
+        let mut groups = HashMap::<VertexId, (Vec<Origin>, Vec<Origin>)>::default();
+        for &e in edges.iter() {
+            let (group, inside) = groups.entry(merged(e.v())).or_default();
+            for v in [e.v(), e.u()].map(Origin::Vertex) {
+                if !group.contains(&v) {
+                    group.push(v);
+                }
+            }
+            inside.push(Origin::Edge(e));
+        }
+        // Whether all the parts came from the same one
+        let same = |origins: &[Origin]| {
+            origins
+                .windows(2)
+                .all(|pair| before.trace(pair[0]) == before.trace(pair[1]))
+        };
+        let mut vertices = HashMap::default();
+        for (u, (group, inside)) in groups {
+            let origin = if same(&group) {
+                Origin::Vertex(u)
+            } else if same(&inside) {
+                inside[0]
+            } else if let Some(i) = before.face_around(
+                &group
+                    .iter()
+                    .filter_map(|v| match v {
+                        Origin::Vertex(v) => Some(*v),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            ) {
+                Origin::Face(i)
+            } else {
+                Origin::Vertex(u)
+            };
+            vertices.insert(u, origin);
+        }
+        // Edges which survive came from the edges that were pulled onto them
+        let mut moved = HashMap::default();
+        for &e in before.edges.iter() {
+            let (v, u) = (merged(e.v()), merged(e.u()));
+            if v != u {
+                let entry = moved.entry((v, u).into()).or_insert(Origin::Edge(e));
+                if e == (v, u).into() {
+                    *entry = Origin::Edge(e);
+                }
+            }
+        }
+        self.inherit(before, &vertices, &moved, &faces);
+        Ok(())
+    }
 
-                let b = transformations[&self.cycles[i][vh]];
-                let a = transformations[&self.cycles[i][vj]];
+    /// Remove edges, merging the faces on either side of each into one that came from the edge
+    pub fn release(&mut self, edges: HashSet<Edge>) -> Result<(), PolyGraphError> {
+        let (cycles, faces): (Vec<_>, Vec<_>) = self
+            .half_edges
+            .remove_edges(|e| edges.contains(e))
+            .into_iter()
+            .map(|(cycle, origin)| (cycle, origin.map_or_else(Origin::Edge, Origin::Face)))
+            .unzip();
+        let half_edges = HalfEdges::from_cycles(&cycles)?;
+        let before = self.snapshot();
+        self.replace(half_edges);
+        self.inherit(before, &Default::default(), &Default::default(), &faces);
+        Ok(())
+    }
 
-                self.cycles[i].insert(vi, a);
-                self.cycles[i].insert(vi, b);
+    /// Replace a vertex with a face, with a corner on each of its edges
+    #[cfg(test)]
+    pub fn split_vertex(&mut self, v: VertexId) -> Result<HashSet<Edge>, PolyGraphError> {
+        if !self.vertices.contains(&v) {
+            return Err(PolyGraphError::MissingVertex(v));
+        }
+        self.split_vertices([v].into_iter().collect())
+    }
 
-                let e: Edge = (a, b).into();
-                new_edges.insert(e);
-                self.connect(e);
+    /// Replace vertices with faces, with a corner on each of their edges.
+    /// Returns the edges around the new faces.
+    fn split_vertices(
+        &mut self,
+        split: HashSet<VertexId>,
+    ) -> Result<HashSet<Edge>, PolyGraphError> {
+        let mut split = split.into_iter().collect::<Vec<_>>();
+        split.sort();
+        // A new vertex at the end of every edge leaving a vertex that's split
+        let mut corners = HashMap::<(VertexId, VertexId), VertexId>::default();
+        let mut fresh = self.fresh_id();
+        for &v in split.iter() {
+            for u in self.half_edges.neighbours(v) {
+                corners.insert((v, u), fresh);
+                fresh += 1;
             }
         }
 
-        self.cycles.push(new_edges.clone().into());
+        // Faces take the corners either side of every vertex that's split
+        let mut cycles = Vec::new();
+        for cycle in self.cycles.iter() {
+            let n = cycle.len();
+            let mut face = Vec::new();
+            for j in 0..n {
+                let (z, v, a) = (cycle[(j + n - 1) % n], cycle[j], cycle[(j + 1) % n]);
+                match (corners.get(&(v, z)), corners.get(&(v, a))) {
+                    (Some(&vz), Some(&va)) => face.extend([vz, va]),
+                    _ => face.push(v),
+                }
+            }
+            cycles.push(Face::new(face));
+        }
+        let mut faces = (0..cycles.len()).map(Origin::Face).collect::<Vec<_>>();
+        // And the corners of each vertex make a face, wound against the turn around it
+        let mut new_edges = HashSet::default();
+        for &v in split.iter() {
+            let mut face = self
+                .half_edges
+                .neighbours(v)
+                .map(|u| corners[&(v, u)])
+                .collect::<Face>();
+            face.reverse();
+            new_edges.extend(face.edges());
+            cycles.push(face);
+            faces.push(Origin::Vertex(v));
+        }
 
-        self.delete(v);
-        new_edges
+        let half_edges = HalfEdges::from_cycles(&cycles)?;
+        let before = self.snapshot();
+        let mut vertices = HashMap::default();
+        for (&(v, _), &corner) in corners.iter() {
+            let position = self.positions.get(&v).copied().unwrap_or_default();
+            self.positions.insert(corner, position);
+            vertices.insert(corner, Origin::Vertex(v));
+        }
+        self.replace(half_edges);
+        self.inherit(before, &vertices, &Default::default(), &faces);
+        Ok(new_edges)
     }
 
     /// `a` ambo
     /// Returns a set of edges to contract
-    pub fn ambo(&mut self) -> HashSet<Edge> {
+    pub fn ambo(&mut self) -> Result<HashSet<Edge>, PolyGraphError> {
         // Truncate
-        let new_edges = self.truncate(None);
-        self.edges
+        let new_edges = self.truncate(None)?;
+        Ok(self
+            .edges
             .clone()
             .difference(&new_edges)
             .map(Edge::clone)
-            .collect()
+            .collect())
     }
 
     /// `k` kis
-    pub fn kis(&mut self, degree: Option<usize>) -> HashSet<Edge> {
+    pub fn kis(&mut self, degree: Option<usize>) -> Result<HashSet<Edge>, PolyGraphError> {
         let edges = self.edges.clone();
-        let mut cycles = self.cycles.clone();
-        if let Some(degree) = degree {
-            cycles.retain(|c| c.len() == degree);
+        let before = self.snapshot();
+        // Each new vertex, and the triangles around it, came from the face it split
+        let fresh = self.fresh_id();
+        let centers: HashMap<VertexId, usize> =
+            self.half_edges.kis(degree, fresh).into_iter().collect();
+        for (&c, &f) in centers.iter() {
+            let centroid = self.face_centroid(f);
+            self.positions.insert(c, centroid);
         }
-        for cycle in cycles {
-            let v = self.insert();
-            let mut vpos = Vec3::zero();
+        self.sync();
+        let vertices = centers
+            .iter()
+            .map(|(&c, &f)| (c, Origin::Face(f)))
+            .collect();
+        let faces = self
+            .cycles
+            .iter()
+            .enumerate()
+            .map(|(i, cycle)| {
+                Origin::Face(
+                    cycle
+                        .iter()
+                        .find_map(|v| centers.get(v))
+                        .copied()
+                        .unwrap_or(i),
+                )
+            })
+            .collect::<Vec<_>>();
+        self.inherit(before, &vertices, &Default::default(), &faces);
+        Ok(edges)
+    }
 
-            for &u in cycle.iter() {
-                self.connect((v, u));
-                vpos += self.positions[&u];
-            }
+    /// `t` truncate
+    pub fn truncate(&mut self, degree: Option<usize>) -> Result<HashSet<Edge>, PolyGraphError> {
+        let vertices = self
+            .vertices
+            .iter()
+            .copied()
+            .filter(|&v| degree.is_none_or(|degree| self.connections(v).len() == degree))
+            .collect();
+        self.split_vertices(vertices)
+    }
 
-            self.positions.insert(v, vpos / cycle.len() as f32);
+    /// Flags which keep every existing vertex where it is
+    fn vertex_flags(&self) -> Flags {
+        let mut flags = Flags::default();
+        for &v in self.vertices.iter() {
+            flags.vertex(Key::Vertex(v), self.positions[&v]);
         }
-        self.pst();
-        self.find_cycles();
-        //self.transactions.insert(1, Transaction::Name('k'));
-        edges
+        flags
     }
 
-    /// `t` truncate
-    pub fn truncate(&mut self, degree: Option<usize>) -> HashSet<Edge> {
-        let mut new_edges = HashSet::default();
-        let mut vertices = self.vertices.clone();
-        if let Some(degree) = degree {
-            vertices.retain(|&v| self.connections(v).len() == degree);
-        }
-        for v in vertices {
-            new_edges.extend(self.split_vertex(v));
+    /// `n` needle = `kd`
+    pub fn needle(&mut self) -> Result<(), PolyGraphError> {
+        let across = self.across();
+        let mut flags = self.vertex_flags();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            flags.vertex(Key::Face(i), self.face_centroid(i));
+            for j in 0..cycle.len() {
+                let (a, b) = (cycle[j], cycle[(j + 1) % cycle.len()]);
+                // One triangle on each side of every edge
+                flags.face(
+                    Key::Side(a, b, 0),
+                    &[Key::Vertex(b), Key::Face(i), Key::Face(far(&across, a, b)?)],
+                );
+            }
         }
-        new_edges
+        self.rebuild(flags)
     }
 
-    /// `o` ortho
-    #[allow(dead_code)]
-    pub fn ortho(&mut self) {
-        for _c in self.cycles.clone() {
-            let _v = self.insert();
+    /// `z` zip = `dk`
+    pub fn zip(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = Flags::default();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let centroid = self.face_centroid(i);
+            let n = cycle.len();
+            for j in 0..n {
+                let (z, a, b) = (cycle[(j + n - 1) % n], cycle[j], cycle[(j + 1) % n]);
+                // A vertex just inside the face from every edge
+                let midpoint = (self.positions[&a] + self.positions[&b]) / 2.0;
+                flags.vertex(Key::Side(a, b, 0), midpoint.lerp(centroid, 0.3));
+                // The face shrinks
+                flags.flag(Key::Face(i), Key::Side(z, a, 0), Key::Side(a, b, 0));
+                // Vertices grow into faces twice their degree
+                flags.flag(Key::Vertex(a), Key::Side(b, a, 0), Key::Side(a, b, 0));
+                flags.flag(Key::Vertex(a), Key::Side(a, b, 0), Key::Side(z, a, 0));
+            }
         }
+        self.rebuild(flags)
     }
 
-    pub fn ordered_face_indices(&self, v: VertexId) -> Vec<usize> {
-        let relevant = (0..self.cycles.len())
-            .filter(|&i| self.cycles[i].containz(&v))
-            .collect::<Vec<usize>>();
-
-        let mut edges = HashMap::default();
-
-        for &i in relevant.iter() {
-            let ui = self.cycles[i].iter().position(|&x| x == v).unwrap();
-            let flen = self.cycles[i].len();
-            // Find the values that came before and after in the face
-            let a = self.cycles[i][(ui + flen - 1) % flen];
-            let b = self.cycles[i][(ui + 1) % flen];
-            edges.insert((a, b).into(), i);
+    /// `o` ortho = `jj`
+    pub fn ortho(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for e in self.edges.iter() {
+            let midpoint = (self.positions[&e.v()] + self.positions[&e.u()]) / 2.0;
+            flags.vertex(Key::Edge(*e), midpoint);
+        }
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            flags.vertex(Key::Face(i), self.face_centroid(i));
+            let n = cycle.len();
+            for j in 0..n {
+                let (z, a, b) = (cycle[(j + n - 1) % n], cycle[j], cycle[(j + 1) % n]);
+                // A quadrilateral for every corner
+                flags.face(
+                    Key::Side(a, b, 0),
+                    &[
+                        Key::Face(i),
+                        Key::Edge((z, a).into()),
+                        Key::Vertex(a),
+                        Key::Edge((a, b).into()),
+                    ],
+                );
+            }
         }
+        self.rebuild(flags)
+    }
 
-        let f: Face = edges.keys().cloned().collect::<HashSet<_>>().into();
+    /// `d` dual, without animating the contraction
+    pub fn dual(&mut self) -> Result<(), PolyGraphError> {
+        let half_edges = self.half_edges.dual()?;
+        // Vertices become faces in order of their indices, and faces become vertices numbered by index
+        let ids = self.half_edges.ids().to_vec();
+        let centroids = (0..self.cycles.len())
+            .map(|f| self.face_centroid(f))
+            .collect::<Vec<_>>();
+        let before = self.snapshot();
+        self.positions = centroids.into_iter().enumerate().collect();
+        self.speeds.clear();
+        self.replace(half_edges);
+        let vertices = self
+            .vertices
+            .iter()
+            .map(|&v| (v, Origin::Face(v)))
+            .collect();
+        let faces = ids.into_iter().map(Origin::Vertex).collect::<Vec<_>>();
+        self.inherit(before, &vertices, &Default::default(), &faces);
+        Ok(())
+    }
 
-        let mut ordered_face_indices = vec![];
-        for i in 0..f.len() {
-            let e: Edge = (f[i], f[(i + 1) % f.len()]).into();
-            let fi = edges.get(&e).unwrap();
-            ordered_face_indices.push(*fi);
+    /// `c` chamfer
+    pub fn chamfer(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let centroid = self.face_centroid(i);
+            let n = cycle.len();
+            for j in 0..n {
+                let (a, b) = (cycle[j], cycle[(j + 1) % n]);
+                let c = cycle[(j + 2) % n];
+                // Each corner of the face moves inwards
+                let (ab, bc) = (Key::Side(a, b, 0), Key::Side(b, c, 0));
+                flags.vertex(ab, self.positions[&a].lerp(centroid, 1.0 / 3.0));
+                flags.flag(Key::Face(i), ab, bc);
+                // Leaving a hexagon behind along each edge
+                let e = Key::Edge((a, b).into());
+                flags.flag(e, bc, ab);
+                flags.flag(e, ab, Key::Vertex(a));
+                flags.flag(e, Key::Vertex(b), bc);
+            }
         }
-
-        ordered_face_indices
+        self.rebuild(flags)
     }
 
-    /// `e` = `aa`
-    pub fn expand(&mut self, snub: bool) -> HashSet<Edge> {
-        let mut new_edges = HashSet::<Edge>::default();
-        let mut face_edges = HashSet::<Edge>::default();
+    /// `u` subdivide = `dcd`
+    pub fn subdivide(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for e in self.edges.iter() {
+            let midpoint = (self.positions[&e.v()] + self.positions[&e.u()]) / 2.0;
+            flags.vertex(Key::Edge(*e), midpoint);
+        }
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let n = cycle.len();
+            for j in 0..n {
+                let (z, a, b) = (cycle[(j + n - 1) % n], cycle[j], cycle[(j + 1) % n]);
+                let (za, ab) = (Key::Edge((z, a).into()), Key::Edge((a, b).into()));
+                // Edge midpoints form a smaller face
+                flags.flag(Key::Face(i), za, ab);
+                // Cutting a triangle off every corner
+                flags.face(Key::Side(a, b, 0), &[Key::Vertex(a), ab, za]);
+            }
+        }
+        self.rebuild(flags)
+    }
 
-        let ordered_face_indices: HashMap<usize, Vec<usize>> = self
+    /// `GC(m,n)` Goldberg-Coxeter, which lays a triangular lattice over
+    /// a polyhedron with triangular faces, or its dual if every vertex has degree three
+    pub fn goldberg_coxeter(&mut self, m: usize, n: usize) -> Result<(), PolyGraphError> {
+        let unsupported = |requirement| PolyGraphError::Unsupported {
+            operation: format!("GC({m},{n})"),
+            requirement,
+        };
+        if m + n == 0 {
+            return Err(unsupported("m and n which aren't both zero"));
+        }
+        if self.cycles.iter().all(|c| c.len() == 3) {
+            self.triangular_lattice(m as i64, n as i64)
+        } else if self
             .vertices
             .iter()
-            .map(|&v| (v, self.ordered_face_indices(v)))
-            .collect();
+            .all(|&v| self.connections(v).len() == 3)
+        {
+            self.dual()?;
+            self.triangular_lattice(m as i64, n as i64)?;
+            self.dual()
+        } else {
+            Err(unsupported("triangular faces or vertices of degree three"))
+        }
+    }
 
-        // For every vertex
-        for v in self.vertices.clone() {
-            let original_position = self.positions[&v];
-            let mut new_face = Face::default();
-            // For every face which contains the vertex
-            for &i in ordered_face_indices.get(&v).unwrap() {
-                // Create a new vertex
-                let u = self.insert();
-                // Replace it in the face
-                self.cycles[i].replace(v, u);
-                // Now replace
-                let ui = self.cycles[i].iter().position(|&x| x == u).unwrap();
-                let flen = self.cycles[i].len();
-                // Find the values that came before and after in the face
-                let a = self.cycles[i][(ui + flen - 1) % flen];
-                let b = self.cycles[i][(ui + 1) % flen];
-                // Remove existing edges which may no longer be accurate
-                new_edges.remove(&(a, v).into());
-                new_edges.remove(&(b, v).into());
-                // Add the new edges which are so yass
-                new_edges.insert((a, u).into());
-                new_edges.insert((b, u).into());
-                // Add u to the new face being formed
-                new_face.push(u);
-                // pos
-                self.positions.insert(u, original_position);
-            }
-            for i in 0..new_face.len() {
-                face_edges.insert((new_face[i], new_face[(i + 1) % new_face.len()]).into());
-            }
-            self.cycles.push(new_face);
-            self.delete(v);
-        }
-
-        let mut solved_edges = HashSet::default();
-
-        // For every triangle / nf edge
-        for a in face_edges.iter() {
-            // find the edge which is parallel to it
-            for b in face_edges.iter() {
-                if !solved_edges.contains(a) && !solved_edges.contains(b) {
-                    if new_edges.contains(&(a.v(), b.v()).into())
-                        && new_edges.contains(&(a.u(), b.u()).into())
-                    {
-                        if snub {
-                            new_edges.insert((a.v(), b.u()).into());
-                            let m = Face::new(vec![a.v(), b.u(), a.u()]);
-                            let n = Face::new(vec![a.v(), b.u(), b.v()]);
-                            self.cycles.push(m);
-                            self.cycles.push(n);
-                        } else {
-                            let quad = Face::new(vec![b.u(), a.u(), a.v(), b.v()]);
-                            self.cycles.push(quad);
-                        }
+    /// Replace every triangle with the patch of the `(m,n)` Eisenstein lattice
+    /// between 0, `m + nω` and `ω(m + nω)`
+    fn triangular_lattice(&mut self, m: i64, n: i64) -> Result<(), PolyGraphError> {
+        let across = self.across();
+        let w = (m, n);
+        let frame = [(0, 0), w, rotate(w)];
+        let area = cross(frame[1], frame[2]) as f32;
 
-                        solved_edges.insert(a);
-                        solved_edges.insert(b);
-                    }
+        // Find the face and coordinates a lattice point belongs to, walking across edges
+        // until it is inside the face, or on an edge running from a lower to a higher vertex
+        let locate = |mut face: usize, mut p: (i64, i64)| -> Result<Key, PolyGraphError> {
+            for _ in 0..self.cycles.len() {
+                let corners = &self.cycles[face];
+                if let Some(k) = frame.iter().position(|&c| c == p) {
+                    return Ok(Key::Vertex(corners[k]));
+                }
+                let (k, side) = (0..3)
+                    .map(|k| {
+                        (
+                            k,
+                            cross(sub(frame[(k + 1) % 3], frame[k]), sub(p, frame[k])),
+                        )
+                    })
+                    .min_by_key(|&(_, side)| side)
+                    .unwrap();
+                let (a, b) = (corners[k], corners[(k + 1) % 3]);
+                if side > 0 || (side == 0 && a < b) {
+                    return Ok(Key::Point(face, p.0, p.1));
+                }
+                // Unfold the neighbouring face onto this one
+                let next = far(&across, a, b)?;
+                let kk = self.cycles[next]
+                    .iter()
+                    .position(|&x| x == b)
+                    .ok_or(PolyGraphError::OpenEdge(a, b))?;
+                let origin = frame[(kk + 1) % 3];
+                let mut offset = sub(p, frame[k]);
+                let target = sub(frame[kk], origin);
+                let mut edge = sub(frame[(k + 1) % 3], frame[k]);
+                while edge != target {
+                    edge = rotate(edge);
+                    offset = rotate(offset);
+                }
+                face = next;
+                p = add(origin, offset);
+            }
+            log::error!("lattice point {p:?} could not be placed");
+            Ok(Key::Point(face, p.0, p.1))
+        };
 
-                    if new_edges.contains(&(a.u(), b.v()).into())
-                        && new_edges.contains(&(a.v(), b.u()).into())
-                    {
-                        if snub {
-                            new_edges.insert((a.u(), b.u()).into());
-                            let m = Face::new(vec![a.u(), b.u(), a.v()]);
-                            let n = Face::new(vec![a.u(), b.u(), b.v()]);
-                            self.cycles.push(m);
-                            self.cycles.push(n);
-                        } else {
-                            let quad = Face::new(vec![a.u(), b.v(), b.u(), a.v()]);
-                            self.cycles.push(quad);
+        let mut flags = Flags::default();
+        let (min, max) = frame.iter().fold((w, w), |(min, max), &(i, j)| {
+            ((min.0.min(i), min.1.min(j)), (max.0.max(i), max.1.max(j)))
+        });
+        for (face, corners) in self.cycles.iter().enumerate() {
+            let positions = corners
+                .iter()
+                .map(|v| self.positions[v])
+                .collect::<Vec<_>>();
+            for i in min.0..max.0 {
+                for j in min.1..max.1 {
+                    let up = [(i, j), (i + 1, j), (i, j + 1)];
+                    let down = [(i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                    for triangle in [up, down] {
+                        // Each small triangle belongs to the face containing its centroid
+                        let centroid = triangle.iter().fold((0, 0), |acc, &p| add(acc, p));
+                        let owned = (0..3).all(|k| {
+                            let edge = sub(frame[(k + 1) % 3], frame[k]);
+                            let side = cross(edge, sub(centroid, scale(frame[k], 3)));
+                            side > 0 || (side == 0 && corners[k] < corners[(k + 1) % 3])
+                        });
+                        if !owned {
+                            continue;
+                        }
+                        let keys = [
+                            locate(face, triangle[0])?,
+                            locate(face, triangle[1])?,
+                            locate(face, triangle[2])?,
+                        ];
+                        for (&p, &key) in triangle.iter().zip(keys.iter()) {
+                            // Barycentric coordinates within the face
+                            let position = (0..3).fold(Vec3::zero(), |acc, k| {
+                                let (b, c) = (frame[(k + 1) % 3], frame[(k + 2) % 3]);
+                                acc + positions[k] * (cross(sub(c, b), sub(p, b)) as f32 / area)
+                            });
+                            flags.vertex(key, position);
                         }
-                        solved_edges.insert(a);
-                        solved_edges.insert(b);
+                        flags.face(Key::Point(face, centroid.0, centroid.1), &keys);
                     }
                 }
             }
         }
-        self.edges = HashSet::default();
-        self.edges.extend(new_edges.clone());
-        self.edges.extend(face_edges);
-        new_edges
+        self.rebuild(flags)
     }
 
-    // `j` join
-    // `z` zip
-    // `g` gyro
-    // `m` meta = `kj`
-    // `o` ortho = `jj`
-    // `n` needle
-    // `k` kis
+    /// `g` gyro = `dsd`
+    pub fn gyro(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            flags.vertex(Key::Face(i), self.face_centroid(i));
+            let n = cycle.len();
+            for j in 0..n {
+                let (z, a, b) = (cycle[(j + n - 1) % n], cycle[j], cycle[(j + 1) % n]);
+                // Every edge is split in three
+                let (za, az) = (Key::Side(z, a, 0), Key::Side(a, z, 0));
+                flags.vertex(za, self.positions[&z].lerp(self.positions[&a], 1.0 / 3.0));
+                flags.vertex(az, self.positions[&a].lerp(self.positions[&z], 1.0 / 3.0));
+                // A pentagon pinwheeling around the face center
+                flags.face(
+                    Key::Side(z, a, 0),
+                    &[Key::Face(i), za, az, Key::Vertex(a), Key::Side(a, b, 0)],
+                );
+            }
+        }
+        self.rebuild(flags)
+    }
+
+    /// `m` meta = `kj`
+    pub fn meta(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for e in self.edges.iter() {
+            let midpoint = (self.positions[&e.v()] + self.positions[&e.u()]) / 2.0;
+            flags.vertex(Key::Edge(*e), midpoint);
+        }
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            flags.vertex(Key::Face(i), self.face_centroid(i));
+            for j in 0..cycle.len() {
+                let (a, b) = (cycle[j], cycle[(j + 1) % cycle.len()]);
+                let e = Key::Edge((a, b).into());
+                // Two triangles for every side
+                flags.face(Key::Side(a, b, 0), &[Key::Face(i), Key::Vertex(a), e]);
+                flags.face(Key::Side(a, b, 1), &[Key::Face(i), e, Key::Vertex(b)]);
+            }
+        }
+        self.rebuild(flags)
+    }
+
+    /// `l` loft
+    pub fn loft(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let centroid = self.face_centroid(i);
+            let n = cycle.len();
+            for j in 0..n {
+                let (z, a, b) = (cycle[(j + n - 1) % n], cycle[j], cycle[(j + 1) % n]);
+                let (za, ab) = (Key::Side(z, a, 0), Key::Side(a, b, 0));
+                flags.vertex(ab, self.positions[&b].lerp(centroid, 0.5));
+                // A smaller copy of the face
+                flags.flag(Key::Face(i), za, ab);
+                // Joined to the original by a ring of quadrilaterals
+                flags.face(ab, &[Key::Vertex(a), Key::Vertex(b), ab, za]);
+            }
+        }
+        self.rebuild(flags)
+    }
+
+    /// `w` whirl
+    pub fn whirl(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let centroid = self.face_centroid(i);
+            let n = cycle.len();
+            for j in 0..n {
+                let (a, b, c) = (cycle[j], cycle[(j + 1) % n], cycle[(j + 2) % n]);
+                // Every edge is split in three
+                let third = self.positions[&a].lerp(self.positions[&b], 1.0 / 3.0);
+                flags.vertex(Key::Side(a, b, 0), third);
+                // With a vertex towards the center of the face from each
+                flags.vertex(Key::Side(a, b, 1), centroid.lerp(third, 1.0 / 3.0));
+                flags.flag(Key::Face(i), Key::Side(a, b, 1), Key::Side(b, c, 1));
+                // A hexagon swirling around the face for every side
+                flags.face(
+                    Key::Side(a, b, 0),
+                    &[
+                        Key::Side(a, b, 1),
+                        Key::Side(a, b, 0),
+                        Key::Side(b, a, 0),
+                        Key::Vertex(b),
+                        Key::Side(b, c, 0),
+                        Key::Side(b, c, 1),
+                    ],
+                );
+            }
+        }
+        self.rebuild(flags)
+    }
+
+    /// `p` propeller
+    pub fn propeller(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let n = cycle.len();
+            for j in 0..n {
+                let (a, b, c) = (cycle[j], cycle[(j + 1) % n], cycle[(j + 2) % n]);
+                let (ab, ba, bc) = (Key::Side(a, b, 0), Key::Side(b, a, 0), Key::Side(b, c, 0));
+                // Every edge is split in three
+                flags.vertex(ab, self.positions[&a].lerp(self.positions[&b], 1.0 / 3.0));
+                // The face twists inside the middle thirds
+                flags.flag(Key::Face(i), ab, bc);
+                // Leaving a blade at every corner
+                flags.face(ab, &[ab, ba, Key::Vertex(b), bc]);
+            }
+        }
+        self.rebuild(flags)
+    }
+
+    /// `q` quinto
+    pub fn quinto(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for e in self.edges.iter() {
+            let midpoint = (self.positions[&e.v()] + self.positions[&e.u()]) / 2.0;
+            flags.vertex(Key::Edge(*e), midpoint);
+        }
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let centroid = self.face_centroid(i);
+            let n = cycle.len();
+            for j in 0..n {
+                let (a, b, c) = (cycle[j], cycle[(j + 1) % n], cycle[(j + 2) % n]);
+                let (ab, bc) = (Key::Side(a, b, 0), Key::Side(b, c, 0));
+                let midpoint = (self.positions[&a] + self.positions[&b]) / 2.0;
+                flags.vertex(ab, midpoint.lerp(centroid, 0.5));
+                // A smaller face between the midpoints and the center
+                flags.flag(Key::Face(i), ab, bc);
+                // And a pentagon around every corner
+                flags.face(
+                    ab,
+                    &[
+                        ab,
+                        Key::Edge((a, b).into()),
+                        Key::Vertex(b),
+                        Key::Edge((b, c).into()),
+                        bc,
+                    ],
+                );
+            }
+        }
+        self.rebuild(flags)
+    }
+
+    /// `L0` join-lace
+    pub fn join_lace(&mut self) -> Result<(), PolyGraphError> {
+        let mut flags = self.vertex_flags();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let centroid = self.face_centroid(i);
+            let n = cycle.len();
+            for j in 0..n {
+                let (a, b, c) = (cycle[j], cycle[(j + 1) % n], cycle[(j + 2) % n]);
+                let (ab, ba, bc) = (Key::Side(a, b, 0), Key::Side(b, a, 0), Key::Side(b, c, 0));
+                let midpoint = (self.positions[&a] + self.positions[&b]) / 2.0;
+                flags.vertex(ab, midpoint.lerp(centroid, 0.5));
+                // A smaller face laced to the corners by triangles
+                flags.flag(Key::Face(i), ab, bc);
+                flags.face(ab, &[ab, Key::Vertex(b), bc]);
+                // Every edge is replaced by a quadrilateral joining both faces
+                flags.face(
+                    Key::Edge((a, b).into()),
+                    &[ab, Key::Vertex(a), ba, Key::Vertex(b)],
+                );
+            }
+        }
+        self.rebuild(flags)
+    }
+
+    /// `r` reflect, turning the polyhedron into its mirror image
+    pub fn reflect(&mut self) {
+        for position in self.positions.values_mut() {
+            position.x = -position.x;
+        }
+        self.half_edges.reverse();
+        self.sync();
+    }
+
+    /// `e` = `aa`, or `s` snub when `snub` splits the faces which grow out of edges in two.
+    /// Returns the edges around the faces that were already there,
+    /// which shrink back down to vertices in the dual.
+    pub fn expand(&mut self, snub: bool) -> Result<HashSet<Edge>, PolyGraphError> {
+        let mesh = &self.half_edges;
+        let id = |h: usize| mesh.ids()[mesh.origin(h)];
+        // A new vertex in every corner of every face, numbered after the half-edge leaving it
+        let fresh = self.fresh_id();
+        let corner = |h: usize| fresh + h;
+
+        // Faces shrink away from their corners
+        let mut cycles = (0..mesh.face_count())
+            .map(|f| mesh.around(f).map(corner).collect::<Face>())
+            .collect::<Vec<_>>();
+        let mut faces = (0..cycles.len()).map(Origin::Face).collect::<Vec<_>>();
+        let mut new_edges = cycles.iter().flat_map(Face::edges).collect::<HashSet<_>>();
+        // Vertices grow into faces, wound against the turn around them
+        for v in 0..mesh.vertex_count() {
+            let mut face = mesh.outgoing(v).map(corner).collect::<Face>();
+            face.reverse();
+            cycles.push(face);
+            faces.push(Origin::Vertex(mesh.ids()[v]));
+        }
+        // And edges into quadrilaterals between the two, or pairs of triangles for a snub
+        for h in (0..mesh.half_edge_count()).filter(|&h| h < mesh.twin(h)) {
+            let t = mesh.twin(h);
+            let edge = Origin::Edge((id(h), id(t)).into());
+            let quad = [
+                corner(mesh.next(h)),
+                corner(h),
+                corner(mesh.next(t)),
+                corner(t),
+            ];
+            if snub {
+                new_edges.insert((quad[0], quad[2]).into());
+                cycles.push(Face::new(vec![quad[0], quad[1], quad[2]]));
+                cycles.push(Face::new(vec![quad[0], quad[2], quad[3]]));
+                faces.extend([edge, edge]);
+            } else {
+                cycles.push(Face::new(quad.to_vec()));
+                faces.push(edge);
+            }
+        }
+
+        let half_edges = HalfEdges::from_cycles(&cycles)?;
+        // Corners start out where their vertex was
+        let vertices = (0..mesh.half_edge_count())
+            .map(|h| (corner(h), Origin::Vertex(id(h))))
+            .collect::<HashMap<_, _>>();
+        let positions = (0..mesh.half_edge_count())
+            .map(|h| {
+                let position = self.positions.get(&id(h)).copied().unwrap_or_default();
+                (corner(h), position)
+            })
+            .collect::<Vec<_>>();
+        let before = self.snapshot();
+        self.positions.extend(positions);
+        self.replace(half_edges);
+        self.inherit(before, &vertices, &Default::default(), &faces);
+        Ok(new_edges)
+    }
+}
+
+/// Face on the far side of the directed edge from `a` to `b`
+fn far(
+    across: &HashMap<(VertexId, VertexId), usize>,
+    a: VertexId,
+    b: VertexId,
+) -> Result<usize, PolyGraphError> {
+    across
+        .get(&(a, b))
+        .copied()
+        .ok_or(PolyGraphError::OpenEdge(a, b))
+}
+
+/// Multiply an Eisenstein integer `a + bω` by ω, rotating it a sixth of a turn
+fn rotate((a, b): (i64, i64)) -> (i64, i64) {
+    (-b, a + b)
+}
+
+fn add(p: (i64, i64), q: (i64, i64)) -> (i64, i64) {
+    (p.0 + q.0, p.1 + q.1)
+}
+
+fn sub(p: (i64, i64), q: (i64, i64)) -> (i64, i64) {
+    (p.0 - q.0, p.1 - q.1)
+}
+
+fn scale(p: (i64, i64), s: i64) -> (i64, i64) {
+    (p.0 * s, p.1 * s)
+}
+
+/// Proportional to the cross product of two Eisenstein integers as vectors in the plane
+fn cross(p: (i64, i64), q: (i64, i64)) -> i64 {
+    p.0 * q.1 - p.1 * q.0
 }
 
 #[cfg(test)]
 mod test {
-    use crate::bones::PolyGraph;
+    use crate::{
+        bones::{settled, Edge, Face, Notation, NotationError, PolyGraph, PolyGraphError},
+        render::message::ConwayMessage,
+    };
+    use rustc_hash::FxHashMap as HashMap;
+    use strum::IntoEnumIterator;
+    use test_case::test_case;
+
+    /// Every face is a closed walk along edges,
+    /// and every edge borders two faces which cross it in opposite directions
+    fn assert_oriented_faces(graph: &PolyGraph) {
+        let mut sides: HashMap<Edge, Vec<(usize, usize)>> = Default::default();
+        for cycle in graph.cycles.iter() {
+            assert!(cycle.len() > 2);
+            for i in 0..cycle.len() {
+                let (a, b) = (cycle[i], cycle[(i + 1) % cycle.len()]);
+                assert!(graph.edges.contains(&(a, b).into()));
+                sides.entry((a, b).into()).or_default().push((a, b));
+            }
+        }
+        assert_eq!(sides.len(), graph.edges.len());
+        for (_, side) in sides {
+            assert_eq!(side.len(), 2);
+            assert_eq!(side[0], (side[1].1, side[1].0));
+        }
+    }
+
+    #[test_case("nT", 8, 18, 12; "nT")]
+    #[test_case("nC", 14, 36, 24; "nC")]
+    #[test_case("nO", 14, 36, 24; "nO")]
+    #[test_case("nD", 32, 90, 60; "nD")]
+    #[test_case("nI", 32, 90, 60; "nI")]
+    #[test_case("zT", 12, 18, 8; "zT")]
+    #[test_case("zC", 24, 36, 14; "zC")]
+    #[test_case("zO", 24, 36, 14; "zO")]
+    #[test_case("zD", 60, 90, 32; "zD")]
+    #[test_case("zI", 60, 90, 32; "zI")]
+    #[test_case("gT", 20, 30, 12; "gT")]
+    #[test_case("gC", 38, 60, 24; "gC")]
+    #[test_case("gO", 38, 60, 24; "gO")]
+    #[test_case("gD", 92, 150, 60; "gD")]
+    #[test_case("gI", 92, 150, 60; "gI")]
+    #[test_case("mT", 14, 36, 24; "mT")]
+    #[test_case("mC", 26, 72, 48; "mC")]
+    #[test_case("mO", 26, 72, 48; "mO")]
+    #[test_case("mD", 62, 180, 120; "mD")]
+    #[test_case("mI", 62, 180, 120; "mI")]
+    #[test_case("oT", 14, 24, 12; "oT")]
+    #[test_case("oC", 26, 48, 24; "oC")]
+    #[test_case("oO", 26, 48, 24; "oO")]
+    #[test_case("oD", 62, 120, 60; "oD")]
+    #[test_case("oI", 62, 120, 60; "oI")]
+    #[test_case("cT", 16, 24, 10; "cT")]
+    #[test_case("cC", 32, 48, 18; "cC")]
+    #[test_case("cD", 80, 120, 42; "cD")]
+    #[test_case("uT", 10, 24, 16; "uT")]
+    #[test_case("uC", 20, 48, 30; "uC")]
+    #[test_case("uI", 42, 120, 80; "uI")]
+    #[test_case("GC(1,1)D", 60, 90, 32; "GC(1,1)D")]
+    #[test_case("GC(2,0)D", 80, 120, 42; "GC(2,0)D")]
+    #[test_case("GC(2,1)D", 140, 210, 72; "GC(2,1)D")]
+    #[test_case("GC(2,1)I", 72, 210, 140; "GC(2,1)I")]
+    #[test_case("GC(3,0)I", 92, 270, 180; "GC(3,0)I")]
+    #[test_case("GC(1,2)O", 30, 84, 56; "GC(1,2)O")]
+    #[test_case("GC(3,1)C", 104, 156, 54; "GC(3,1)C")]
+    #[test_case("lT", 16, 30, 16; "lT")]
+    #[test_case("lC", 32, 60, 30; "lC")]
+    #[test_case("lD", 80, 150, 72; "lD")]
+    #[test_case("wT", 28, 42, 16; "wT")]
+    #[test_case("wC", 56, 84, 30; "wC")]
+    #[test_case("wD", 140, 210, 72; "wD")]
+    #[test_case("pT", 16, 30, 16; "pT")]
+    #[test_case("pC", 32, 60, 30; "pC")]
+    #[test_case("pD", 80, 150, 72; "pD")]
+    #[test_case("qT", 22, 36, 16; "qT")]
+    #[test_case("qC", 44, 72, 30; "qC")]
+    #[test_case("qD", 110, 180, 72; "qD")]
+    #[test_case("L0T", 16, 36, 22; "L0T")]
+    #[test_case("L0C", 32, 72, 42; "L0C")]
+    #[test_case("L0D", 80, 180, 102; "L0D")]
+    #[test_case("rgC", 38, 60, 24; "rgC")]
+    fn counts(notation: &str, vertices: usize, edges: usize, faces: usize) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        assert_eq!(graph.vertices.len(), vertices);
+        assert_eq!(graph.edges.len(), edges);
+        assert_eq!(graph.cycles.len(), faces);
+        let name = notation.parse::<Notation>().unwrap().simplify().to_string();
+        assert_eq!(graph.name, name);
+        assert_oriented_faces(&graph);
+    }
+
+    /// Faces wind counterclockwise from outside after every operation,
+    /// so their normals point away from the middle of a settled layout
+    #[test]
+    fn outward() {
+        let cube = settled("C");
+        for conway in ConwayMessage::iter().filter(|c| c.notation() != "GC(0,0)") {
+            let mut graph = cube.clone();
+            graph.operate(&conway).unwrap();
+            assert_oriented_faces(&graph);
+            graph.springs();
+            graph.settle();
+            for i in 0..graph.cycles.len() {
+                assert!(
+                    graph.face_normal(i).dot(graph.face_centroid(i)) > 0.0,
+                    "{conway} face {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dodecahedron() {
+        let graph = PolyGraph::dodecahedron();
+        assert_eq!(graph.vertices.len(), 20);
+        assert!(graph.cycles.iter().all(|c| c.len() == 5));
+        assert!(graph
+            .vertices
+            .iter()
+            .all(|&v| graph.connections(v).len() == 3));
+    }
+
+    #[test_case("zC", &[(4, 6), (6, 8)]; "zC")]
+    #[test_case("nC", &[(3, 24)]; "nC")]
+    #[test_case("oC", &[(4, 24)]; "oC")]
+    #[test_case("gC", &[(5, 24)]; "gC")]
+    #[test_case("cC", &[(4, 6), (6, 12)]; "cC")]
+    #[test_case("GC(2,1)D", &[(5, 12), (6, 60)]; "GC(2,1)D")]
+    #[test_case("GC(2,1)I", &[(3, 140)]; "GC(2,1)I")]
+    #[test_case("lC", &[(4, 30)]; "lC")]
+    #[test_case("wC", &[(4, 6), (6, 24)]; "wC")]
+    #[test_case("pC", &[(4, 30)]; "pC")]
+    #[test_case("qC", &[(4, 6), (5, 24)]; "qC")]
+    #[test_case("L0C", &[(3, 24), (4, 18)]; "L0C")]
+    fn face_sizes(notation: &str, sizes: &[(usize, usize)]) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        let mut counts: HashMap<usize, usize> = Default::default();
+        for cycle in graph.cycles.iter() {
+            *counts.entry(cycle.len()).or_default() += 1;
+        }
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort();
+        assert_eq!(counts, sizes);
+    }
+
+    #[test]
+    fn reflect() {
+        let mut graph: PolyGraph = "gC".parse().unwrap();
+        let positions = graph.positions.clone();
+        graph.operate(&ConwayMessage::Reflect).unwrap();
+        // Gyro of the dual is the mirror image
+        assert_eq!(graph.name, "gO");
+        assert_oriented_faces(&graph);
+        for (v, position) in graph.positions.iter() {
+            assert_eq!(position.x, -positions[v].x);
+        }
+        graph.operate(&ConwayMessage::Reflect).unwrap();
+        assert_eq!(graph.name, "gC");
+        assert_eq!(graph.positions, positions);
+    }
+
+    #[test]
+    fn goldberg_coxeter_needs_triangles_or_three_valence() {
+        let mut graph: PolyGraph = "aC".parse().unwrap();
+        assert_eq!(
+            graph.goldberg_coxeter(2, 1),
+            Err(PolyGraphError::Unsupported {
+                operation: "GC(2,1)".into(),
+                requirement: "triangular faces or vertices of degree three",
+            })
+        );
+        assert_eq!(graph.name, "aC");
+        assert_eq!(graph.vertices.len(), 12);
+        assert_eq!(graph.cycles.len(), 14);
+    }
+
+    #[test]
+    fn unsupported_notation() {
+        assert!(matches!(
+            "GC(1,1)aC".parse::<PolyGraph>(),
+            Err(NotationError::Operation(PolyGraphError::Unsupported { .. }))
+        ));
+    }
+
+    #[test]
+    fn broken_face() {
+        // A hexagon with two triangles folded onto it leaves the edge between them open
+        let faces = vec![
+            Face::new(vec![0, 1, 2, 3, 4, 5]),
+            Face::new(vec![0, 2, 1]),
+            Face::new(vec![3, 5, 4]),
+        ];
+        assert!(matches!(
+            PolyGraph::from_cycles(faces),
+            Err(PolyGraphError::OpenEdge(..))
+        ));
+        // Faces are the same wherever they start and whichever way they wind
+        let face = Face::new(vec![3, 1, 2]);
+        assert_eq!(face, Face::new(vec![2, 1, 3]));
+        assert_eq!(
+            face.cmp(&Face::new(vec![1, 3, 2])),
+            std::cmp::Ordering::Equal
+        );
+        assert!(face < Face::new(vec![1, 2, 4]));
+    }
+
+    #[test]
+    fn missing_vertex() {
+        let mut graph = PolyGraph::prism(4);
+        assert_eq!(graph.split_vertex(8), Err(PolyGraphError::MissingVertex(8)));
+        assert_eq!(graph.vertices.len(), 8);
+    }
 
     #[test]
     fn truncate() {
         let mut shape = PolyGraph::icosahedron();
-        shape.truncate(None);
+        shape.truncate(None).unwrap();
     }
 
     #[test]
@@ -314,8 +1070,7 @@ mod test {
         assert_eq!(graph.vertices.len(), 8);
         assert_eq!(graph.edges.len(), 12);
 
-        graph.contract_edge((0, 1));
-        graph.pst();
+        graph.contract_edge((0, 1)).unwrap();
 
         assert_eq!(graph.vertices.len(), 7);
         assert_eq!(graph.edges.len(), 11);
@@ -327,8 +1082,7 @@ mod test {
         assert_eq!(graph.vertices.len(), 8);
         assert_eq!(graph.edges.len(), 12);
 
-        graph.split_vertex(0);
-        graph.pst();
+        graph.split_vertex(0).unwrap();
 
         assert_eq!(graph.vertices.len(), 10);
         assert_eq!(graph.edges.len(), 15);
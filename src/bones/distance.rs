@@ -0,0 +1,236 @@
+use crate::bones::{PolyGraph, VertexId};
+use rustc_hash::FxHashMap as HashMap;
+
+/// Marks pairs of vertices with no path between them
+const UNREACHABLE: u16 = u16::MAX;
+/// Fewest vertices worth spreading the searches across threads for
+const PARALLEL_VERTICES: usize = 512;
+/// Most springs holding each vertex away from the far side of the polyhedron
+const FAR_SPRINGS: usize = 4;
+
+/// Neighbours of every vertex, as slices of one list
+struct Adjacency {
+    starts: Vec<usize>,
+    neighbours: Vec<usize>,
+}
+
+impl Adjacency {
+    /// Neighbours of every vertex of the graph, numbered in order of their ids
+    fn new(graph: &PolyGraph) -> (Vec<VertexId>, HashMap<VertexId, usize>, Self) {
+        let mut ids = graph.vertices.iter().copied().collect::<Vec<_>>();
+        ids.sort();
+        let index: HashMap<VertexId, usize> =
+            ids.iter().enumerate().map(|(i, &v)| (v, i)).collect();
+
+        let mut starts = vec![0; ids.len() + 1];
+        for e in graph.edges.iter() {
+            starts[index[&e.v()] + 1] += 1;
+            starts[index[&e.u()] + 1] += 1;
+        }
+        for i in 0..ids.len() {
+            starts[i + 1] += starts[i];
+        }
+        let mut filled = starts.clone();
+        let mut neighbours = vec![0; starts[ids.len()]];
+        for e in graph.edges.iter() {
+            let (v, u) = (index[&e.v()], index[&e.u()]);
+            neighbours[filled[v]] = u;
+            neighbours[filled[u]] = v;
+            filled[v] += 1;
+            filled[u] += 1;
+        }
+        (ids, index, Adjacency { starts, neighbours })
+    }
+
+    fn of(&self, v: usize) -> &[usize] {
+        &self.neighbours[self.starts[v]..self.starts[v + 1]]
+    }
+}
+
+impl PolyGraph {
+    /// Springs between vertices up to two edges apart, and between those at least
+    /// one less than the diameter apart, with the number of edges between the ends of each.
+    /// Alongside them, `far_springs` from each vertex to the few furthest from it.
+    /// Searches only keep what they find from one vertex at a time,
+    /// so the distance between every pair is never stored.
+    pub fn springs(&mut self) {
+        let (ids, _, adjacency) = Adjacency::new(self);
+        let found = search(&adjacency, threads(ids.len()));
+        let diameter = found.diameter;
+        let edge = |(v, u, d): (usize, usize, usize)| ((ids[v], ids[u]).into(), d);
+        self.springs = found
+            .springs
+            .into_iter()
+            .filter(|&(_, _, d)| d <= 2 || d >= diameter.saturating_sub(1))
+            .map(edge)
+            .collect();
+        self.far_springs = found.far.into_iter().map(edge).collect();
+
+        log::debug!(
+            "v_len: {} | v2: {} | springs: {} | far springs: {}",
+            self.vertices.len(),
+            (self.vertices.len() as f32).powi(2),
+            self.springs.len(),
+            self.far_springs.len()
+        );
+    }
+}
+
+/// Threads to spread searches from each of `n` vertices across
+fn threads(n: usize) -> usize {
+    if n < PARALLEL_VERTICES || cfg!(target_arch = "wasm32") {
+        1
+    } else {
+        std::thread::available_parallelism().map_or(1, |threads| threads.get())
+    }
+}
+
+/// Search from every vertex, splitting them between `threads`
+fn search(adjacency: &Adjacency, threads: usize) -> Springs {
+    let n = adjacency.starts.len() - 1;
+    let mut shares = (0..threads).map(|_| Vec::new()).collect::<Vec<_>>();
+    for source in 0..n {
+        shares[source % threads].push(source);
+    }
+    if threads == 1 {
+        search_springs(adjacency, shares.pop().unwrap_or_default())
+    } else {
+        std::thread::scope(|scope| {
+            let searches = shares
+                .into_iter()
+                .map(|share| scope.spawn(|| search_springs(adjacency, share)))
+                .collect::<Vec<_>>();
+            searches
+                .into_iter()
+                .map(|search| {
+                    search
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .fold(Springs::default(), Springs::join)
+        })
+    }
+}
+
+/// Springs found by searches from some of the vertices
+#[derive(Default)]
+struct Springs {
+    /// Those up to two edges apart, and those which might be across the diameter
+    springs: Vec<(usize, usize, usize)>,
+    /// The few furthest from each source
+    far: Vec<(usize, usize, usize)>,
+    /// Longest distance found
+    diameter: usize,
+}
+
+impl Springs {
+    fn join(mut self, other: Self) -> Self {
+        self.springs.extend(other.springs);
+        self.far.extend(other.far);
+        self.diameter = self.diameter.max(other.diameter);
+        self
+    }
+}
+
+/// Breadth first search from each source, returning the vertices up to two edges away
+/// from it, those at most one less than its furthest, and the few furthest, with their distances
+fn search_springs(adjacency: &Adjacency, sources: Vec<usize>) -> Springs {
+    let n = adjacency.starts.len() - 1;
+    let mut depths = vec![UNREACHABLE; n];
+    let mut order = Vec::with_capacity(n);
+    let mut found = Springs::default();
+    for source in sources {
+        depths.fill(UNREACHABLE);
+        depths[source] = 0;
+        order.clear();
+        order.push(source);
+        let mut next = 0;
+        while let Some(&v) = order.get(next) {
+            next += 1;
+            let depth = depths[v].saturating_add(1).min(UNREACHABLE - 1);
+            for &u in adjacency.of(v) {
+                if depths[u] == UNREACHABLE {
+                    depths[u] = depth;
+                    order.push(u);
+                }
+            }
+        }
+        // Searches find the furthest vertices last
+        let furthest = order.last().map_or(0, |&u| depths[u] as usize);
+        found.diameter = found.diameter.max(furthest);
+        // Pairs are found from both ends, so only keep them from one.
+        // Nothing is further apart than the diameter, which is at least as far as this,
+        // so any pair across it is found here.
+        let springs = order.iter().skip(1).filter(|&&u| {
+            let d = depths[u] as usize;
+            u > source && (d <= 2 || d + 1 >= furthest)
+        });
+        found
+            .springs
+            .extend(springs.map(|&u| (source, u, depths[u] as usize)));
+        let far = order
+            .iter()
+            .rev()
+            .take(FAR_SPRINGS)
+            .filter(|&&u| depths[u] > 2);
+        found
+            .far
+            .extend(far.map(|&u| (source, u, depths[u] as usize)));
+    }
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::{search, Adjacency, Springs, FAR_SPRINGS};
+    use crate::bones::{Edge, PolyGraph};
+    use rustc_hash::FxHashMap as HashMap;
+    use test_case::test_case;
+
+    /// Springs join everything up to two edges apart and everything across the diameter,
+    /// and far springs join each vertex to a few of the furthest
+    #[test_case("tD"; "tD")]
+    #[test_case("kkD"; "kkD")]
+    #[test_case("P12"; "P12")]
+    fn springs(notation: &str) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        let oracle = graph.floyd();
+        let diameter = oracle.values().copied().max().unwrap_or_default();
+        let expected = oracle
+            .iter()
+            .filter(|(_, &d)| d <= 2 || d + 1 >= diameter)
+            .map(|(e, &d)| (*e, d))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(graph.springs, expected);
+
+        for (e, d) in graph.far_springs.iter() {
+            assert_eq!(oracle[e], *d, "{e}");
+        }
+        assert!(graph.far_springs.len() <= FAR_SPRINGS * graph.vertices.len());
+        for &v in graph.vertices.iter() {
+            let furthest = |distances: &mut dyn Iterator<Item = (&Edge, &usize)>| {
+                distances
+                    .filter(|(e, _)| e.v() == v || e.u() == v)
+                    .map(|(_, &d)| d)
+                    .max()
+            };
+            assert_eq!(
+                furthest(&mut graph.far_springs.iter()),
+                furthest(&mut oracle.iter())
+            );
+        }
+    }
+
+    /// Spreading the searches across threads finds the same springs
+    #[test]
+    fn parallel() {
+        let graph: PolyGraph = "tkD".parse().unwrap();
+        let (_, _, adjacency) = Adjacency::new(&graph);
+        let sorted = |mut found: Springs| {
+            found.springs.sort();
+            found.far.sort();
+            (found.springs, found.far, found.diameter)
+        };
+        assert_eq!(sorted(search(&adjacency, 1)), sorted(search(&adjacency, 7)));
+    }
+}
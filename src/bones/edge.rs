@@ -26,10 +26,7 @@ impl Edge {
         self.id().1
     }
 
-    pub fn contains(&self, v: VertexId) -> bool {
-        self.v == v || self.u == v
-    }
-
+    #[cfg(test)]
     pub fn other(&self, v: VertexId) -> Option<VertexId> {
         if self.v == v {
             Some(self.u)
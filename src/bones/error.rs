@@ -0,0 +1,61 @@
+use crate::bones::VertexId;
+use std::fmt::Display;
+
+/// Why an operation couldn't be carried out on a graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolyGraphError {
+    /// A vertex which isn't part of the graph
+    MissingVertex(VertexId),
+    /// An edge with a face on only one side
+    OpenEdge(VertexId, VertexId),
+    /// The faces around a vertex don't form a single ring
+    OpenVertex(VertexId),
+    /// Edges which don't join up into a single cycle
+    BrokenCycle,
+    /// A face, by index into `cycles`, without three distinct vertices
+    DegenerateFace(usize),
+    /// Consecutive vertices of a face which aren't joined by an edge
+    MissingEdge(VertexId, VertexId),
+    /// An edge bordering more than two faces
+    NonManifoldEdge(VertexId, VertexId),
+    /// A vertex which isn't on any edge
+    OrphanVertex(VertexId),
+    /// `V - E + F`, which is two for anything shaped like a sphere
+    EulerCharacteristic(i64),
+    /// An operation which only works on some polyhedra
+    Unsupported {
+        operation: String,
+        requirement: &'static str,
+    },
+}
+
+impl Display for PolyGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use PolyGraphError::*;
+        match self {
+            MissingVertex(v) => f.write_fmt(format_args!("vertex {v} is not in the graph")),
+            OpenEdge(v, u) => f.write_fmt(format_args!("edge {v}-{u} has a face on only one side")),
+            OpenVertex(v) => f.write_fmt(format_args!("faces around vertex {v} don't close up")),
+            BrokenCycle => f.write_str("edges don't join up into a single face"),
+            DegenerateFace(i) => f.write_fmt(format_args!(
+                "face {i} has fewer than three distinct vertices"
+            )),
+            MissingEdge(v, u) => f.write_fmt(format_args!(
+                "face runs from {v} to {u} without an edge between them"
+            )),
+            NonManifoldEdge(v, u) => {
+                f.write_fmt(format_args!("edge {v}-{u} borders more than two faces"))
+            }
+            OrphanVertex(v) => f.write_fmt(format_args!("vertex {v} isn't on any edge")),
+            EulerCharacteristic(x) => f.write_fmt(format_args!(
+                "Euler characteristic is {x}, a closed polyhedron has 2"
+            )),
+            Unsupported {
+                operation,
+                requirement,
+            } => f.write_fmt(format_args!("{operation} needs {requirement}")),
+        }
+    }
+}
+
+impl std::error::Error for PolyGraphError {}
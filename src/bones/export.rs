@@ -0,0 +1,317 @@
+use crate::{
+    bones::PolyGraph,
+    render::{color::RGBA, message::ExportMessage},
+};
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+use ultraviolet::Vec3;
+
+/// Vertex positions, and faces as indices into them
+pub type Mesh = (Vec<Vec3>, Vec<Vec<usize>>);
+
+// Mesh export
+impl PolyGraph {
+    /// Faces indexing into a list of positions,
+    /// wound counterclockwise when seen from outside the polyhedron
+    pub fn mesh(&self) -> Mesh {
+        let mut graph = self.clone();
+        graph.orient_outward();
+
+        let mut vertices = graph.vertices.iter().copied().collect::<Vec<_>>();
+        vertices.sort();
+        let positions = vertices
+            .iter()
+            .map(|v| graph.positions.get(v).copied().unwrap_or_default())
+            .collect::<Vec<_>>();
+        let faces = graph
+            .cycles
+            .iter()
+            .map(|cycle| {
+                cycle
+                    .iter()
+                    .filter_map(|v| vertices.binary_search(v).ok())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        (positions, faces)
+    }
+
+    /// Write the polyhedron to `path` in the given format.
+    /// Returns every file written, since OBJ colors live in a separate material library.
+    pub fn export(
+        &self,
+        format: &ExportMessage,
+        colors: Option<&[RGBA]>,
+        path: &Path,
+    ) -> std::io::Result<Vec<PathBuf>> {
+        use ExportMessage::*;
+        let mesh = self.mesh();
+        let contents = match format {
+            Obj => {
+                let library = path.with_extension("mtl");
+                let name = library.file_name().and_then(|name| name.to_str());
+                let colors = colors.zip(name);
+                // Only replace a library along with the OBJ that uses it
+                if colors.is_some() && library.exists() && !path.exists() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::AlreadyExists,
+                        format!("{} already exists", library.display()),
+                    ));
+                }
+                std::fs::write(path, obj(&self.name, &mesh, colors))?;
+                if let Some((colors, _)) = colors {
+                    std::fs::write(&library, mtl(colors))?;
+                    return Ok(vec![path.to_path_buf(), library]);
+                }
+                return Ok(vec![path.to_path_buf()]);
+            }
+            Off => off(&mesh, colors).into_bytes(),
+            StlAscii => stl_ascii(&self.name, &mesh).into_bytes(),
+            StlBinary => stl_binary(&self.name, &mesh),
+            Ply => ply(&self.name, &mesh, colors).into_bytes(),
+            NetSvg => self.net().svg(colors, false).into_bytes(),
+            NetSvgTabs => self.net().svg(colors, true).into_bytes(),
+            MeasurementsCsv => self.measure().csv().into_bytes(),
+        };
+        std::fs::write(path, contents)?;
+        Ok(vec![path.to_path_buf()])
+    }
+}
+
+/// Wavefront OBJ, with faces using the materials from `mtl` when there are colors
+fn obj(name: &str, (positions, faces): &Mesh, colors: Option<(&[RGBA], &str)>) -> String {
+    let mut obj = String::from("# Polyblade\n");
+    if let Some((_, library)) = colors {
+        let _ = writeln!(obj, "mtllib {library}");
+    }
+    let _ = writeln!(obj, "o {name}");
+    for p in positions.iter() {
+        let _ = writeln!(obj, "v {} {} {}", p.x, p.y, p.z);
+    }
+    for (i, face) in faces.iter().enumerate() {
+        if let Some((colors, _)) = colors {
+            let _ = writeln!(obj, "usemtl color{}", i % colors.len().max(1));
+        }
+        let indices = face.iter().map(|v| (v + 1).to_string()).collect::<Vec<_>>();
+        let _ = writeln!(obj, "f {}", indices.join(" "));
+    }
+    obj
+}
+
+/// Material library for OBJ face colors, one material per face
+fn mtl(colors: &[RGBA]) -> String {
+    let mut mtl = String::from("# Polyblade\n");
+    for (i, color) in colors.iter().enumerate() {
+        let [r, g, b] = unit(color);
+        let _ = writeln!(mtl, "newmtl color{i}\nKd {r} {g} {b}\n");
+    }
+    mtl
+}
+
+/// Object File Format, with face colors after each face's vertices
+fn off((positions, faces): &Mesh, colors: Option<&[RGBA]>) -> String {
+    let mut off = String::from("OFF\n");
+    let edges = faces.iter().map(Vec::len).sum::<usize>() / 2;
+    let _ = writeln!(off, "{} {} {edges}", positions.len(), faces.len());
+    for p in positions.iter() {
+        let _ = writeln!(off, "{} {} {}", p.x, p.y, p.z);
+    }
+    for (i, face) in faces.iter().enumerate() {
+        let _ = write!(off, "{}", face.len());
+        for v in face.iter() {
+            let _ = write!(off, " {v}");
+        }
+        if let Some(color) = colors.and_then(|colors| colors.get(i)) {
+            let _ = write!(off, " {} {} {}", color.r, color.g, color.b);
+        }
+        off.push('\n');
+    }
+    off
+}
+
+/// Stanford PLY, in ASCII so that it can be read by hand
+fn ply(name: &str, (positions, faces): &Mesh, colors: Option<&[RGBA]>) -> String {
+    let mut ply = String::from("ply\nformat ascii 1.0\n");
+    let _ = writeln!(ply, "comment Polyblade {name}");
+    let _ = writeln!(ply, "element vertex {}", positions.len());
+    ply.push_str("property float x\nproperty float y\nproperty float z\n");
+    let _ = writeln!(ply, "element face {}", faces.len());
+    ply.push_str("property list uchar int vertex_indices\n");
+    if colors.is_some() {
+        ply.push_str("property uchar red\nproperty uchar green\nproperty uchar blue\n");
+    }
+    ply.push_str("end_header\n");
+    for p in positions.iter() {
+        let _ = writeln!(ply, "{} {} {}", p.x, p.y, p.z);
+    }
+    for (i, face) in faces.iter().enumerate() {
+        let _ = write!(ply, "{}", face.len());
+        for v in face.iter() {
+            let _ = write!(ply, " {v}");
+        }
+        if let Some(colors) = colors {
+            let color = colors.get(i).copied().unwrap_or_default();
+            let _ = write!(ply, " {} {} {}", color.r, color.g, color.b);
+        }
+        ply.push('\n');
+    }
+    ply
+}
+
+/// STL in ASCII. STL has no colors, only triangles.
+fn stl_ascii(name: &str, mesh: &Mesh) -> String {
+    let mut stl = format!("solid {name}\n");
+    for [a, b, c] in triangles(mesh) {
+        let n = normal(a, b, c);
+        let _ = writeln!(stl, "  facet normal {} {} {}", n.x, n.y, n.z);
+        stl.push_str("    outer loop\n");
+        for p in [a, b, c] {
+            let _ = writeln!(stl, "      vertex {} {} {}", p.x, p.y, p.z);
+        }
+        stl.push_str("    endloop\n  endfacet\n");
+    }
+    let _ = writeln!(stl, "endsolid {name}");
+    stl
+}
+
+/// STL in binary, which is much smaller than ASCII
+fn stl_binary(name: &str, mesh: &Mesh) -> Vec<u8> {
+    let triangles = triangles(mesh);
+    let mut stl = vec![0u8; 80];
+    let header = format!("Polyblade {name}");
+    let length = header.len().min(80);
+    stl[..length].copy_from_slice(&header.as_bytes()[..length]);
+    stl.extend((triangles.len() as u32).to_le_bytes());
+    for [a, b, c] in triangles {
+        for p in [normal(a, b, c), a, b, c] {
+            for x in [p.x, p.y, p.z] {
+                stl.extend(x.to_le_bytes());
+            }
+        }
+        // Attribute byte count, which nothing agrees on the meaning of
+        stl.extend(0u16.to_le_bytes());
+    }
+    stl
+}
+
+/// Split every face into triangles the same way the renderer does,
+/// fanning around the centroid of anything bigger than a quad
+fn triangles((positions, faces): &Mesh) -> Vec<[Vec3; 3]> {
+    let mut triangles = Vec::new();
+    for face in faces.iter() {
+        let face = face.iter().map(|&v| positions[v]).collect::<Vec<_>>();
+        match face.len() {
+            3 => triangles.push([face[0], face[1], face[2]]),
+            4 => triangles.extend([[face[0], face[1], face[2]], [face[2], face[3], face[0]]]),
+            n => {
+                let centroid = face.iter().fold(Vec3::zero(), |a, &b| a + b) / n as f32;
+                triangles.extend((0..n).map(|i| [face[i], face[(i + 1) % n], centroid]));
+            }
+        }
+    }
+    triangles
+}
+
+fn normal(a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    let normal = (b - a).cross(c - a);
+    if normal.mag_sq() == 0.0 {
+        normal
+    } else {
+        normal.normalized()
+    }
+}
+
+/// Color channels between zero and one
+fn unit(color: &RGBA) -> [f32; 3] {
+    [color.r, color.g, color.b].map(|c| c as f32 / 255.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bones::settled;
+    use test_case::test_case;
+
+    #[test_case("C"; "C")]
+    #[test_case("tI"; "tI")]
+    #[test_case("gC"; "gC")]
+    #[test_case("jA5"; "jA5")]
+    fn outward(notation: &str) {
+        let graph = settled(notation);
+        let (positions, faces) = graph.mesh();
+        let center = positions.iter().fold(Vec3::zero(), |a, &b| a + b) / positions.len() as f32;
+        for face in faces.iter() {
+            let face = face.iter().map(|&v| positions[v]).collect::<Vec<_>>();
+            let centroid = face.iter().fold(Vec3::zero(), |a, &b| a + b) / face.len() as f32;
+            let normal = (0..face.len())
+                .map(|i| face[i].cross(face[(i + 1) % face.len()]))
+                .fold(Vec3::zero(), |a, b| a + b);
+            assert!(normal.dot(centroid - center) > 0.0);
+        }
+    }
+
+    #[test]
+    fn formats() {
+        let graph: PolyGraph = "tT".parse().unwrap();
+        let mesh = graph.mesh();
+        let colors = [RGBA::new(255, 0, 0, 255); 8];
+
+        let obj = obj(&graph.name, &mesh, Some((&colors, "tT.mtl")));
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 12);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("usemtl")).count(), 8);
+        assert_eq!(
+            mtl(&colors)
+                .lines()
+                .filter(|l| l.starts_with("Kd 1 0 0"))
+                .count(),
+            8
+        );
+
+        let off = off(&mesh, Some(&colors));
+        assert_eq!(off.lines().nth(1), Some("12 8 18"));
+        assert_eq!(off.lines().count(), 2 + 12 + 8);
+        assert!(off.lines().last().unwrap().ends_with(" 255 0 0"));
+
+        let ply = ply(&graph.name, &mesh, None);
+        assert!(ply.contains("element vertex 12\n"));
+        assert!(ply.contains("element face 8\n"));
+        assert!(!ply.contains("red"));
+
+        // Four triangles, and four hexagons fanned into six each
+        let triangles = 4 + 4 * 6;
+        let ascii = stl_ascii(&graph.name, &mesh);
+        assert_eq!(ascii.matches("facet normal").count(), triangles);
+        assert_eq!(stl_binary(&graph.name, &mesh).len(), 84 + 50 * triangles);
+    }
+
+    /// A material library that some other OBJ uses is left alone
+    #[test]
+    fn library() {
+        let dir = std::env::temp_dir().join(format!("polyblade-library-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, library) = (dir.join("cube.obj"), dir.join("cube.mtl"));
+        std::fs::write(&library, "other").unwrap();
+
+        let graph: PolyGraph = "C".parse().unwrap();
+        let colors = [RGBA::new(255, 0, 0, 255); 6];
+        let error = graph
+            .export(&ExportMessage::Obj, Some(&colors), &path)
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert!(!path.exists());
+        assert_eq!(std::fs::read_to_string(&library).unwrap(), "other");
+
+        // Replacing the OBJ replaces its library too
+        std::fs::write(&path, "").unwrap();
+        let written = graph.export(&ExportMessage::Obj, Some(&colors), &path);
+        assert_eq!(written.unwrap(), vec![path, library.clone()]);
+        assert!(std::fs::read_to_string(&library)
+            .unwrap()
+            .contains("Kd 1 0 0"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,13 +1,16 @@
 use crate::bones::{Edge, VertexId};
 use rustc_hash::FxHashSet as HashSet;
 use std::{
+    cmp::Ordering,
     hash::Hash,
     ops::{Index, IndexMut},
     slice::SliceIndex,
     vec::IntoIter,
 };
 
-#[derive(Debug, Default, Clone, PartialOrd, Ord)]
+/// Vertices in order around a face. Two faces are equal when they have the same vertices,
+/// whichever one they start from and whichever way they wind.
+#[derive(Debug, Default, Clone)]
 pub struct Face(Vec<VertexId>);
 
 impl Face {
@@ -30,19 +33,6 @@ impl Face {
         edges
     }
 
-    pub fn replace(&mut self, old: VertexId, new: VertexId) {
-        if self.0.contains(&new) && self.0.contains(&old) {
-            self.remove(self.0.iter().position(|&x| x == old).unwrap());
-        } else {
-            self.0 = self
-                .0
-                .clone()
-                .into_iter()
-                .map(|x| if x == old { new } else { x })
-                .collect();
-        }
-    }
-
     #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
@@ -52,45 +42,27 @@ impl Face {
         self.0.len()
     }
 
-    pub fn iter(&self) -> std::slice::Iter<usize> {
+    pub fn iter(&self) -> std::slice::Iter<'_, usize> {
         self.0.iter()
     }
 
-    pub fn remove(&mut self, index: usize) -> VertexId {
-        self.0.remove(index)
+    pub fn reverse(&mut self) {
+        self.0.reverse()
     }
 
-    pub fn insert(&mut self, index: usize, v: VertexId) {
-        self.0.insert(index, v)
+    /// Vertices in ascending order, which is all that equality looks at
+    fn sorted(&self) -> Vec<VertexId> {
+        let mut vertices = self.0.clone();
+        vertices.sort();
+        vertices
     }
 
-    pub fn push(&mut self, value: VertexId) {
-        self.0.push(value)
-    }
-}
-
-impl From<HashSet<Edge>> for Face {
-    fn from(value: HashSet<Edge>) -> Self {
-        let mut edges: Vec<Edge> = value.into_iter().collect();
-        let mut first = false;
-        let mut face = vec![edges[0].v()];
-        while !edges.is_empty() {
-            let v = if first {
-                *face.first().unwrap()
-            } else {
-                *face.last().unwrap()
-            };
-            if let Some(i) = edges.iter().position(|e| e.contains(v)) {
-                let next = edges[i].other(v).unwrap();
-                if !face.contains(&next) {
-                    face.push(next);
-                }
-                edges.remove(i);
-            } else {
-                first ^= true;
-            }
-        }
-        Self::new(face)
+    /// Whether `b` comes directly after `a` when walking around the face
+    pub fn follows(&self, a: VertexId, b: VertexId) -> bool {
+        self.0
+            .iter()
+            .position(|&x| x == a)
+            .is_some_and(|i| self.0[(i + 1) % self.0.len()] == b)
     }
 }
 
@@ -143,10 +115,18 @@ impl PartialEq for Face {
 impl Eq for Face {}
 impl Hash for Face {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        let mut edges = self.edges().into_iter().collect::<Vec<_>>();
-        edges.sort();
-        for edge in edges {
-            edge.hash(state);
-        }
+        self.sorted().hash(state);
+    }
+}
+
+impl PartialOrd for Face {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Face {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorted().cmp(&other.sorted())
     }
 }
@@ -0,0 +1,153 @@
+use crate::bones::{PolyGraph, VertexId};
+use rustc_hash::FxHashMap as HashMap;
+use std::collections::VecDeque;
+
+/// Describes a polyhedron without depending on how its vertices are labeled.
+/// Two polyhedra have the same fingerprint exactly when they are isomorphic,
+/// or mirror images of each other.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(Vec<usize>);
+
+impl PolyGraph {
+    /// Canonical code of the planar embedding, kept until the structure next changes
+    pub fn fingerprint(&self) -> &Fingerprint {
+        self.fingerprint.get_or_init(|| self.find_fingerprint())
+    }
+
+    /// Every directed edge is tried as a starting point, in both orientations,
+    /// labeling vertices in the order a breadth first search around the faces
+    /// finds them and listing their neighbours. The smallest code wins.
+    fn find_fingerprint(&self) -> Fingerprint {
+        let graph = self;
+        let clockwise = graph.rotations();
+        let counter: HashMap<_, _> = clockwise.iter().map(|(&(a, z), &b)| ((a, b), z)).collect();
+
+        let mut best: Option<Vec<usize>> = None;
+        for &start in clockwise.keys() {
+            for next in [&clockwise, &counter] {
+                if let Some(code) = code(start, next, best.as_deref()) {
+                    best = Some(code);
+                }
+            }
+        }
+        Fingerprint(best.unwrap_or_default())
+    }
+}
+
+/// Ends the list of neighbours of each vertex
+const SEPARATOR: usize = usize::MAX;
+
+/// Code found starting from a directed edge, or `None` as soon as it is clear
+/// that it would be no smaller than the best found so far
+fn code(
+    start: (VertexId, VertexId),
+    next: &HashMap<(VertexId, VertexId), VertexId>,
+    best: Option<&[usize]>,
+) -> Option<Vec<usize>> {
+    let mut labels = HashMap::default();
+    labels.insert(start.0, 0);
+    let mut queue = VecDeque::from([start]);
+    let mut code = vec![];
+    // Whether the code so far matches the start of the best one
+    let mut tied = best.is_some();
+    let mut push = |code: &mut Vec<usize>, symbol: usize| {
+        if tied {
+            let other = best.and_then(|best| best.get(code.len())).copied();
+            match Some(symbol).cmp(&other) {
+                std::cmp::Ordering::Greater => return false,
+                std::cmp::Ordering::Less => tied = false,
+                std::cmp::Ordering::Equal => {}
+            }
+        }
+        code.push(symbol);
+        true
+    };
+
+    while let Some((v, first)) = queue.pop_front() {
+        let mut u = first;
+        loop {
+            let label = match labels.get(&u) {
+                Some(&label) => label,
+                None => {
+                    let label = labels.len();
+                    labels.insert(u, label);
+                    queue.push_back((u, v));
+                    label
+                }
+            };
+            if !push(&mut code, label) {
+                return None;
+            }
+            u = next[&(v, u)];
+            if u == first {
+                break;
+            }
+        }
+        if !push(&mut code, SEPARATOR) {
+            return None;
+        }
+    }
+
+    // Otherwise it is identical to the best one
+    (!tied).then_some(code)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bones::{Face, PolyGraph};
+    use test_case::test_case;
+
+    #[test_case("dD", "I"; "dD")]
+    #[test_case("aaT", "eT"; "aaT")]
+    #[test_case("aT", "O"; "aT")]
+    #[test_case("jT", "C"; "jT")]
+    #[test_case("gT", "D"; "gT")]
+    #[test_case("dA4", "dA4"; "dA4")]
+    #[test_case("gC", "rgC"; "mirror image")]
+    #[test_case("P4", "C"; "P4")]
+    #[test_case("Y3", "T"; "Y3")]
+    #[test_case("tdO", "tC"; "tdO")]
+    fn isomorphic(a: &str, b: &str) {
+        let a: PolyGraph = a.parse().unwrap();
+        let b: PolyGraph = b.parse().unwrap();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test_case("C", "O"; "dual")]
+    #[test_case("tT", "aT"; "same vertex count")]
+    #[test_case("P6", "A4"; "same counts")]
+    #[test_case("kC", "kO"; "kC")]
+    fn distinct(a: &str, b: &str) {
+        let a: PolyGraph = a.parse().unwrap();
+        let b: PolyGraph = b.parse().unwrap();
+        assert_ne!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test]
+    fn relabeled() {
+        let graph: PolyGraph = "tkC".parse().unwrap();
+        let shift = |v: usize| (v * 7 + 3) % 1000 + 5000;
+        let mut relabeled = graph.clone();
+        relabeled.vertices = graph.vertices.iter().map(|&v| shift(v)).collect();
+        relabeled.edges = graph
+            .edges
+            .iter()
+            .map(|e| (shift(e.v()), shift(e.u())).into())
+            .collect();
+        for cycle in relabeled.cycles.iter_mut() {
+            *cycle = Face::new(cycle.iter().map(|&v| shift(v)).collect());
+        }
+        assert_eq!(graph.fingerprint(), relabeled.fingerprint());
+    }
+
+    #[test]
+    fn cached() {
+        let mut graph: PolyGraph = "P5".parse().unwrap();
+        assert!(graph.fingerprint.get().is_none());
+        let prism = graph.fingerprint().clone();
+        assert!(graph.fingerprint.get().is_some());
+        graph.truncate(None).unwrap();
+        assert!(graph.fingerprint.get().is_none());
+        assert_ne!(*graph.fingerprint(), prism);
+    }
+}
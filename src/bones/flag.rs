@@ -0,0 +1,158 @@
+use crate::bones::{Edge, Face, HalfEdges, Origin, PolyGraph, PolyGraphError, VertexId};
+use rustc_hash::FxHashMap as HashMap;
+use std::collections::{hash_map::Entry, BTreeMap};
+use ultraviolet::Vec3;
+
+/// Names a vertex or face of the polyhedron being built
+/// after the part of the old polyhedron it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    /// An existing vertex
+    Vertex(VertexId),
+    /// An existing face, by index into `cycles`
+    Face(usize),
+    /// An existing edge
+    Edge(Edge),
+    /// The directed edge from one vertex to another, numbered when it holds several things
+    Side(VertexId, VertexId, usize),
+    /// Coordinates within a face
+    Point(usize, i64, i64),
+}
+
+impl Key {
+    /// Part of the old polyhedron this is named after
+    fn origin(self) -> Origin {
+        match self {
+            Key::Vertex(v) => Origin::Vertex(v),
+            Key::Face(i) | Key::Point(i, _, _) => Origin::Face(i),
+            Key::Edge(e) => Origin::Edge(e),
+            Key::Side(a, b, _) => Origin::Edge((a, b).into()),
+        }
+    }
+}
+
+/// Describes a new polyhedron as directed edges ("flags") around each of its faces,
+/// so that faces which several old faces contribute to are stitched together
+#[derive(Debug, Default)]
+pub struct Flags {
+    positions: HashMap<Key, Vec3>,
+    faces: BTreeMap<Key, HashMap<Key, Key>>,
+}
+
+impl Flags {
+    /// Declare a vertex and where it starts out
+    pub fn vertex(&mut self, key: Key, position: Vec3) {
+        self.positions.insert(key, position);
+    }
+
+    /// Within `face`, `from` is followed by `to`
+    pub fn flag(&mut self, face: Key, from: Key, to: Key) {
+        self.faces.entry(face).or_default().insert(from, to);
+    }
+
+    /// A whole face at once
+    pub fn face(&mut self, face: Key, vertices: &[Key]) {
+        for i in 0..vertices.len() {
+            self.flag(face, vertices[i], vertices[(i + 1) % vertices.len()]);
+        }
+    }
+}
+
+impl PolyGraph {
+    /// Turn every face around if they all wind clockwise when seen from outside,
+    /// which leaves the volume they enclose negative
+    pub fn orient_outward(&mut self) {
+        if self.volume() < 0.0 {
+            self.half_edges.reverse();
+            self.sync();
+        }
+    }
+
+    /// Faces on the far side of each directed edge of an oriented polyhedron
+    pub fn across(&self) -> HashMap<(VertexId, VertexId), usize> {
+        self.cycles
+            .iter()
+            .enumerate()
+            .flat_map(|(i, cycle)| {
+                (0..cycle.len()).map(move |j| ((cycle[(j + 1) % cycle.len()], cycle[j]), i))
+            })
+            .collect()
+    }
+
+    /// The next neighbour clockwise around a vertex from another, for an oriented polyhedron
+    pub fn rotations(&self) -> HashMap<(VertexId, VertexId), VertexId> {
+        self.cycles
+            .iter()
+            .flat_map(|cycle| {
+                let n = cycle.len();
+                (0..n).map(move |j| ((cycle[j], cycle[(j + n - 1) % n]), cycle[(j + 1) % n]))
+            })
+            .collect()
+    }
+
+    /// Replace the structure of the graph with the one described by `flags`.
+    /// Existing vertices that are kept hold on to their positions.
+    /// Nothing changes if any face fails to close up, or the faces don't make up a polyhedron.
+    pub fn rebuild(&mut self, flags: Flags) -> Result<(), PolyGraphError> {
+        // Walk every face before touching the graph
+        let mut faces = Vec::new();
+        for (&key, next) in flags.faces.iter() {
+            let Some(&start) = next.keys().min() else {
+                continue;
+            };
+            let mut face = vec![start];
+            let mut current = next[&start];
+            while current != start {
+                match next.get(&current) {
+                    Some(&following)
+                        if face.len() < next.len() && flags.positions.contains_key(&current) =>
+                    {
+                        face.push(current);
+                        current = following;
+                    }
+                    _ => return Err(PolyGraphError::BrokenCycle),
+                }
+            }
+            if !flags.positions.contains_key(&start) {
+                return Err(PolyGraphError::BrokenCycle);
+            }
+            faces.push((key, face));
+        }
+
+        let mut ids: HashMap<Key, VertexId> = Default::default();
+        for key in flags.positions.keys() {
+            if let Key::Vertex(v) = key {
+                if self.vertices.contains(v) {
+                    ids.insert(*key, *v);
+                }
+            }
+        }
+        let mut keys = flags.positions.keys().collect::<Vec<_>>();
+        keys.sort();
+        let mut fresh = self.fresh_id();
+        let mut positions = Vec::new();
+        for &key in keys {
+            if let Entry::Vacant(entry) = ids.entry(key) {
+                positions.push((fresh, flags.positions[&key]));
+                entry.insert(fresh);
+                fresh += 1;
+            }
+        }
+        let cycles = faces
+            .iter()
+            .map(|(_, face)| Face::new(face.iter().map(|key| ids[key]).collect()))
+            .collect::<Vec<_>>();
+        let half_edges = HalfEdges::from_cycles(&cycles)?;
+
+        let before = self.snapshot();
+        self.positions.extend(positions);
+        self.replace(half_edges);
+        let vertices = ids.iter().map(|(key, &v)| (v, key.origin())).collect();
+        let faces = faces
+            .iter()
+            .map(|(key, _)| key.origin())
+            .collect::<Vec<_>>();
+        self.inherit(before, &vertices, &Default::default(), &faces);
+        Ok(())
+    }
+}
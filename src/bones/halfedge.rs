@@ -0,0 +1,485 @@
+use crate::bones::{Edge, Face, PolyGraph, PolyGraphError, VertexId};
+use rustc_hash::FxHashMap as HashMap;
+
+/// A polyhedron as half-edges, with every edge split into the two directed halves
+/// that run along the faces on either side of it. Vertices, faces and half-edges are
+/// dense indices, so neighbourhoods are found by following indices instead of searching.
+/// Every face winds the same way, so each half-edge runs the other way to its twin.
+#[derive(Debug, Clone, Default)]
+pub struct HalfEdges {
+    /// Vertex each half-edge leaves from
+    origin: Vec<usize>,
+    /// Half-edge running the other way along the same edge
+    twin: Vec<usize>,
+    /// Half-edge after this one around its face
+    next: Vec<usize>,
+    /// Face each half-edge runs around
+    face: Vec<usize>,
+    /// One half-edge leaving each vertex
+    vertex_edge: Vec<usize>,
+    /// One half-edge running around each face
+    face_edge: Vec<usize>,
+    /// Id of each vertex in the graph
+    ids: Vec<VertexId>,
+    /// Index of each vertex id
+    index: HashMap<VertexId, usize>,
+}
+
+impl HalfEdges {
+    /// Join faces, given as dense vertex indices all wound the same way, along their edges
+    pub fn new(ids: Vec<VertexId>, faces: Vec<Vec<usize>>) -> Result<Self, PolyGraphError> {
+        use PolyGraphError::*;
+        let mut mesh = HalfEdges {
+            vertex_edge: vec![usize::MAX; ids.len()],
+            index: ids.iter().enumerate().map(|(i, &v)| (v, i)).collect(),
+            ids,
+            ..Default::default()
+        };
+        let mut directed: HashMap<(usize, usize), usize> = HashMap::default();
+        for (f, vertices) in faces.iter().enumerate() {
+            let mut sorted = vertices.clone();
+            sorted.sort();
+            sorted.dedup();
+            if sorted.len() < 3 || sorted.len() < vertices.len() {
+                return Err(DegenerateFace(f));
+            }
+            let first = mesh.origin.len();
+            mesh.face_edge.push(first);
+            for (i, &v) in vertices.iter().enumerate() {
+                let h = first + i;
+                let u = vertices[(i + 1) % vertices.len()];
+                if directed.insert((v, u), h).is_some() {
+                    return Err(NonManifoldEdge(mesh.ids[v], mesh.ids[u]));
+                }
+                mesh.origin.push(v);
+                mesh.next.push(first + (i + 1) % vertices.len());
+                mesh.face.push(f);
+                mesh.vertex_edge[v] = h;
+            }
+        }
+        mesh.twin = (0..mesh.origin.len())
+            .map(|h| {
+                let (v, u) = (mesh.origin[h], mesh.target(h));
+                directed
+                    .get(&(u, v))
+                    .copied()
+                    .ok_or(OpenEdge(mesh.ids[v], mesh.ids[u]))
+            })
+            .collect::<Result<_, _>>()?;
+
+        // Every half-edge leaving a vertex has to be reached by turning around it
+        let mut leaving = vec![0; mesh.vertex_edge.len()];
+        for &v in mesh.origin.iter() {
+            leaving[v] += 1;
+        }
+        for (v, leaving) in leaving.into_iter().enumerate() {
+            if leaving == 0 {
+                return Err(OrphanVertex(mesh.ids[v]));
+            }
+            if mesh.outgoing(v).count() != leaving {
+                return Err(OpenVertex(mesh.ids[v]));
+            }
+        }
+        Ok(mesh)
+    }
+
+    /// Join faces, given as vertex ids all wound the same way, along their edges
+    pub fn from_cycles(cycles: &[Face]) -> Result<Self, PolyGraphError> {
+        let mut ids = cycles
+            .iter()
+            .flat_map(|cycle| cycle.iter().copied())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        let faces = cycles
+            .iter()
+            .map(|cycle| {
+                cycle
+                    .iter()
+                    .map(|v| ids.binary_search(v).unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        HalfEdges::new(ids, faces)
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_edge.len()
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.face_edge.len()
+    }
+
+    pub fn half_edge_count(&self) -> usize {
+        self.origin.len()
+    }
+
+    /// Id of each vertex, by index
+    pub fn ids(&self) -> &[VertexId] {
+        &self.ids
+    }
+
+    /// Vertex a half-edge leaves from
+    pub fn origin(&self, h: usize) -> usize {
+        self.origin[h]
+    }
+
+    /// Half-edge running the other way along the same edge
+    pub fn twin(&self, h: usize) -> usize {
+        self.twin[h]
+    }
+
+    /// Half-edge after this one around its face
+    pub fn next(&self, h: usize) -> usize {
+        self.next[h]
+    }
+
+    /// Vertex a half-edge arrives at
+    pub fn target(&self, h: usize) -> usize {
+        self.origin[self.next[h]]
+    }
+
+    /// Half-edges leaving a vertex, turning the same way around it as faces are wound
+    pub fn outgoing(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        let start = self.vertex_edge[v];
+        let mut h = Some(start);
+        std::iter::from_fn(move || {
+            let current = h?;
+            let following = self.next[self.twin[current]];
+            h = (following != start).then_some(following);
+            Some(current)
+        })
+    }
+
+    /// Half-edges running around a face, in order
+    pub fn around(&self, f: usize) -> impl Iterator<Item = usize> + '_ {
+        let start = self.face_edge[f];
+        let mut h = Some(start);
+        std::iter::from_fn(move || {
+            let current = h?;
+            let following = self.next[current];
+            h = (following != start).then_some(following);
+            Some(current)
+        })
+    }
+
+    /// Faces meeting at a vertex, in order around it
+    pub fn vertex_faces(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.outgoing(v).map(|h| self.face[h])
+    }
+
+    /// Vertices of a face, in order
+    pub fn face_vertices(&self, f: usize) -> impl Iterator<Item = usize> + '_ {
+        self.around(f).map(|h| self.origin[h])
+    }
+
+    /// Ids of the vertices joined to the vertex with id `v`, in order around it
+    pub fn neighbours(&self, v: VertexId) -> impl Iterator<Item = VertexId> + '_ {
+        self.index
+            .get(&v)
+            .into_iter()
+            .flat_map(|&v| self.outgoing(v))
+            .map(|h| self.ids[self.target(h)])
+    }
+
+    /// Every edge, by the ids of its ends
+    pub fn edges(&self) -> impl Iterator<Item = Edge> + '_ {
+        (0..self.origin.len())
+            .filter(|&h| h < self.twin[h])
+            .map(|h| Edge::from((self.ids[self.origin[h]], self.ids[self.target(h)])))
+    }
+
+    /// Every face, by the ids of its vertices
+    pub fn cycles(&self) -> impl Iterator<Item = Face> + '_ {
+        (0..self.face_count())
+            .map(|f| Face::new(self.face_vertices(f).map(|v| self.ids[v]).collect()))
+    }
+
+    /// `k` kis on one face, splitting it into triangles around a new vertex `id`.
+    /// Only the face's own half-edges change, and the new ones go on the end.
+    pub fn split_face(&mut self, f: usize, id: VertexId) -> usize {
+        let c = self.vertex_edge.len();
+        self.ids.push(id);
+        self.index.insert(id, c);
+
+        let sides = self.around(f).collect::<Vec<_>>();
+        let n = sides.len();
+        // Each side h becomes the triangle h, then back to the centre, then out again
+        let spokes = self.origin.len();
+        let (inward, outward) = (|i| spokes + 2 * i, |i| spokes + 2 * i + 1);
+        for (i, &h) in sides.iter().enumerate() {
+            let triangle = if i == 0 { f } else { self.face_edge.len() };
+            if i > 0 {
+                self.face_edge.push(h);
+            }
+            // From the end of this side in to the centre
+            self.origin.push(self.target(h));
+            self.next.push(outward(i));
+            self.face.push(triangle);
+            self.twin.push(outward((i + 1) % n));
+            // From the centre out to the start of this side
+            self.origin.push(c);
+            self.next.push(h);
+            self.face.push(triangle);
+            self.twin.push(inward((i + n - 1) % n));
+
+            self.next[h] = inward(i);
+            self.face[h] = triangle;
+        }
+        self.face_edge[f] = sides[0];
+        self.vertex_edge.push(outward(0));
+        c
+    }
+
+    /// `k` kis, on every face or only those with `degree` sides, numbering the new
+    /// vertices from `id`. Returns the id of each new vertex along with the face it split.
+    pub fn kis(&mut self, degree: Option<usize>, mut id: VertexId) -> Vec<(VertexId, usize)> {
+        let mut centers = Vec::new();
+        for f in 0..self.face_count() {
+            if degree.is_none_or(|degree| self.around(f).count() == degree) {
+                self.split_face(f, id);
+                centers.push((id, f));
+                id += 1;
+            }
+        }
+        centers
+    }
+
+    /// `d` dual, with a vertex numbered after every face and a face around every vertex,
+    /// in order of their indices
+    pub fn dual(&self) -> Result<HalfEdges, PolyGraphError> {
+        let faces = (0..self.vertex_count())
+            .map(|v| self.vertex_faces(v).collect())
+            .collect();
+        HalfEdges::new((0..self.face_count()).collect(), faces)
+    }
+
+    /// Wind every face the other way
+    pub fn reverse(&mut self) {
+        let mut previous = vec![0; self.next.len()];
+        for (h, &next) in self.next.iter().enumerate() {
+            previous[next] = h;
+        }
+        // Each half-edge now leaves from where it used to arrive
+        self.origin = (0..self.origin.len()).map(|h| self.target(h)).collect();
+        self.next = previous;
+        for h in self.vertex_edge.iter_mut() {
+            *h = self.twin[*h];
+        }
+    }
+
+    /// Renumber the faces in order of `key`, keeping faces with the same key in order
+    pub fn sort_faces<K: Ord>(&mut self, key: impl Fn(usize) -> K) {
+        let mut order = (0..self.face_count()).collect::<Vec<_>>();
+        order.sort_by_key(|&f| key(f));
+        let mut renumbered = vec![0; order.len()];
+        for (i, &f) in order.iter().enumerate() {
+            renumbered[f] = i;
+        }
+        for f in self.face.iter_mut() {
+            *f = renumbered[*f];
+        }
+        self.face_edge = order.into_iter().map(|f| self.face_edge[f]).collect();
+    }
+
+    /// Faces left when the edges in `removed` are taken out, merging the faces on either
+    /// side of each. Returns each face's vertex ids, with the face it was part of when no
+    /// edge was removed from it, or else the first edge it lost.
+    pub fn remove_edges(
+        &self,
+        removed: impl Fn(&Edge) -> bool,
+    ) -> Vec<(Face, Result<usize, Edge>)> {
+        let edge = |h: usize| Edge::from((self.ids[self.origin[h]], self.ids[self.target(h)]));
+        let gone = (0..self.origin.len())
+            .map(|h| removed(&edge(h)))
+            .collect::<Vec<_>>();
+        let mut visited = vec![false; self.origin.len()];
+        let mut faces = Vec::new();
+        for start in 0..self.origin.len() {
+            if gone[start] || visited[start] {
+                continue;
+            }
+            let mut cycle = Vec::new();
+            let mut origin = Ok(self.face[start]);
+            let mut h = start;
+            loop {
+                visited[h] = true;
+                cycle.push(self.ids[self.origin[h]]);
+                // Turn around the end of any removed edges, onto the next one that's left
+                let mut following = self.next[h];
+                while gone[following] {
+                    if origin.is_ok() {
+                        origin = Err(edge(following));
+                    }
+                    following = self.next[self.twin[following]];
+                }
+                h = following;
+                if h == start {
+                    break;
+                }
+            }
+            faces.push((Face::new(cycle), origin));
+        }
+        faces
+    }
+}
+
+impl PolyGraph {
+    /// A polyhedron made of `cycles`, turned where they need to be to wind the same way
+    pub fn from_cycles(mut cycles: Vec<Face>) -> Result<Self, PolyGraphError> {
+        orient(&mut cycles);
+        let mut graph = PolyGraph {
+            edge_length: 1.0,
+            ..Default::default()
+        };
+        graph.replace(HalfEdges::from_cycles(&cycles)?);
+        Ok(graph)
+    }
+
+    /// Replace the structure of the graph with `half_edges`, keeping the
+    /// positions and speeds of any vertices that are still around
+    pub fn replace(&mut self, half_edges: HalfEdges) {
+        self.half_edges = half_edges;
+        self.sync();
+    }
+
+    /// Number the faces from the most sides to the fewest
+    pub fn sort_faces(&mut self) {
+        let sides = self.cycles.iter().map(Face::len).collect::<Vec<_>>();
+        self.half_edges.sort_faces(|f| usize::MAX - sides[f]);
+        self.sync();
+    }
+
+    /// Bring `vertices`, `edges` and `cycles` up to date with `half_edges`
+    pub fn sync(&mut self) {
+        self.vertices = self.half_edges.ids().iter().copied().collect();
+        self.edges = self.half_edges.edges().collect();
+        self.cycles = self.half_edges.cycles().collect();
+        self.symmetry = Default::default();
+        self.fingerprint = Default::default();
+        self.positions.retain(|v, _| self.vertices.contains(v));
+        self.speeds.retain(|v, _| self.vertices.contains(v));
+        for &v in self.vertices.iter() {
+            self.speeds.entry(v).or_default();
+        }
+    }
+}
+
+/// Turn faces around so that each edge is crossed in opposite directions
+/// by the two faces it borders, starting from the way the first of each piece winds
+pub fn orient(cycles: &mut [Face]) {
+    let mut edge_faces: HashMap<Edge, Vec<usize>> = Default::default();
+    for (i, cycle) in cycles.iter().enumerate() {
+        for e in cycle.edges() {
+            edge_faces.entry(e).or_default().push(i);
+        }
+    }
+
+    let mut oriented = vec![false; cycles.len()];
+    for start in 0..cycles.len() {
+        if oriented[start] {
+            continue;
+        }
+        oriented[start] = true;
+        let mut queue = vec![start];
+        while let Some(i) = queue.pop() {
+            let cycle = cycles[i].clone();
+            for j in 0..cycle.len() {
+                let (a, b) = (cycle[j], cycle[(j + 1) % cycle.len()]);
+                for &k in edge_faces[&(a, b).into()].iter() {
+                    if !oriented[k] {
+                        // The neighbour has to run from b to a
+                        if cycles[k].follows(a, b) {
+                            cycles[k].reverse();
+                        }
+                        oriented[k] = true;
+                        queue.push(k);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HalfEdges;
+    use crate::bones::{PolyGraph, PolyGraphError};
+    use test_case::test_case;
+
+    #[test_case("T"; "T")]
+    #[test_case("tI"; "tI")]
+    #[test_case("gC"; "gC")]
+    #[test_case("jA5"; "jA5")]
+    fn adjacency(notation: &str) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        let mesh = graph.half_edges.clone();
+        assert_eq!(mesh.vertex_count(), graph.vertices.len());
+        assert_eq!(mesh.origin.len(), 2 * graph.edges.len());
+        assert_eq!(mesh.face_count(), graph.cycles.len());
+        for v in 0..mesh.vertex_count() {
+            let mut neighbours = mesh
+                .outgoing(v)
+                .map(|h| mesh.ids[mesh.target(h)])
+                .collect::<Vec<_>>();
+            let mut connections = graph
+                .edges
+                .iter()
+                .filter_map(|e| e.other(mesh.ids[v]))
+                .collect::<Vec<_>>();
+            neighbours.sort();
+            connections.sort();
+            assert_eq!(neighbours, connections);
+        }
+        for h in 0..mesh.origin.len() {
+            assert_eq!(mesh.twin[mesh.twin[h]], h);
+            assert_eq!(mesh.target(mesh.twin[h]), mesh.origin[h]);
+            assert_ne!(mesh.face[h], mesh.face[mesh.twin[h]]);
+        }
+
+        let mut copy = graph.clone();
+        copy.replace(HalfEdges::from_cycles(&graph.cycles).unwrap());
+        assert_eq!(copy.fingerprint(), graph.fingerprint());
+    }
+
+    #[test]
+    fn open() {
+        let mut cycles = PolyGraph::prism(4).cycles;
+        cycles.remove(0);
+        assert!(matches!(
+            PolyGraph::from_cycles(cycles),
+            Err(PolyGraphError::OpenEdge(..))
+        ));
+    }
+
+    /// Turning every face around twice leaves the mesh as it was
+    #[test]
+    fn reverse() {
+        let graph: PolyGraph = "gC".parse().unwrap();
+        let mut mesh = graph.half_edges.clone();
+        mesh.reverse();
+        let reversed = mesh.cycles().collect::<Vec<_>>();
+        for (cycle, reversed) in graph.cycles.iter().zip(reversed.iter()) {
+            assert_eq!(cycle, reversed);
+            assert!(reversed.follows(cycle[1], cycle[0]));
+        }
+        assert!(HalfEdges::from_cycles(&reversed).is_ok());
+        mesh.reverse();
+        assert!(mesh.cycles().eq(graph.cycles.iter().cloned()));
+    }
+
+    /// Local rewrites keep working on meshes far bigger than the graph layouts can handle
+    #[test]
+    fn large() {
+        let mut mesh = PolyGraph::prism(4).half_edges;
+        while mesh.vertex_count() < 10_000 {
+            mesh.kis(None, mesh.vertex_count());
+            mesh = mesh.dual().unwrap();
+        }
+        let euler =
+            mesh.vertex_count() as i64 - mesh.origin.len() as i64 / 2 + mesh.face_count() as i64;
+        assert_eq!(euler, 2);
+        assert!((0..mesh.vertex_count()).all(|v| mesh.outgoing(v).count() == 3));
+    }
+}
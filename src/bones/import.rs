@@ -0,0 +1,500 @@
+use crate::bones::{Edge, Face, PolyGraph, PolyGraphError, VertexId};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use std::{fmt::Display, path::Path, str::FromStr};
+use ultraviolet::Vec3;
+
+/// File extensions which can be imported
+pub const IMPORT_EXTENSIONS: &[&str] = &["off", "obj"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    /// The file couldn't be read
+    Io(String),
+    /// Not a file extension we know how to read
+    Format(String),
+    /// No vertices or faces
+    Empty,
+    /// A line that doesn't follow the format
+    Syntax { line: usize, message: String },
+    /// A face refers to a vertex that was never given
+    MissingVertex { face: usize, vertex: isize },
+    /// A face with fewer than three distinct vertices
+    DegenerateFace(usize),
+    /// An edge which borders only one face, so the surface has a hole
+    Open(VertexId, VertexId),
+    /// An edge which borders more than two faces
+    NonManifold(VertexId, VertexId),
+    /// The faces make up more than one separate surface
+    Disconnected,
+    /// `V - E + F` is 2 for surfaces with no handles
+    EulerCharacteristic(i64),
+    /// Removing these vertices splits the surface in two,
+    /// or there were too few vertices to need removing
+    NotThreeConnected(Vec<VertexId>),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ImportError::*;
+        match self {
+            Io(error) => f.write_str(error),
+            Format(path) => f.write_fmt(format_args!("'{path}' is not an OFF or OBJ file")),
+            Empty => f.write_str("no polyhedron in file"),
+            Syntax { line, message } => f.write_fmt(format_args!("line {line}: {message}")),
+            MissingVertex { face, vertex } => {
+                f.write_fmt(format_args!("face {face} uses missing vertex {vertex}"))
+            }
+            DegenerateFace(face) => {
+                f.write_fmt(format_args!("face {face} has fewer than three vertices"))
+            }
+            Open(v, u) => f.write_fmt(format_args!("edge {v}-{u} only borders one face")),
+            NonManifold(v, u) => {
+                f.write_fmt(format_args!("edge {v}-{u} borders more than two faces"))
+            }
+            Disconnected => f.write_str("surface is in more than one piece"),
+            EulerCharacteristic(euler) => f.write_fmt(format_args!(
+                "V - E + F is {euler}, surfaces with holes through them can't be polyhedra"
+            )),
+            NotThreeConnected(separator) if separator.is_empty() => {
+                f.write_str("polyhedra need at least four vertices")
+            }
+            NotThreeConnected(separator) => f.write_fmt(format_args!(
+                "removing vertices {separator:?} splits the polyhedron"
+            )),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+// Mesh import
+impl PolyGraph {
+    /// Read an OFF or OBJ file, named after the file
+    pub fn import(path: &Path) -> Result<PolyGraph, ImportError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| ImportError::Io(e.to_string()))?;
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        let mut graph = match extension.as_deref() {
+            Some("off") => Self::from_off(&contents),
+            Some("obj") => Self::from_obj(&contents),
+            _ => Err(ImportError::Format(path.display().to_string())),
+        }?;
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            graph.name = stem.to_string();
+        }
+        Ok(graph)
+    }
+
+    /// Object File Format, ignoring any colors
+    pub fn from_off(contents: &str) -> Result<PolyGraph, ImportError> {
+        let mut lines = significant_lines(contents, '#');
+        let (mut line, mut header) = lines.next().ok_or(ImportError::Empty)?;
+        // `OFF` and its variants like `COFF` can be followed by the counts on the same line
+        if let Some(keyword) = header
+            .split_whitespace()
+            .next()
+            .filter(|k| k.ends_with("OFF"))
+        {
+            header = header[keyword.len()..].trim();
+            if header.is_empty() {
+                (line, header) = lines.next().ok_or(ImportError::Empty)?;
+            }
+        }
+        let counts = numbers::<usize>(line, header, 2)?;
+
+        let mut positions = Vec::new();
+        for _ in 0..counts[0] {
+            let (line, text) = lines.next().ok_or(ImportError::Syntax {
+                line,
+                message: format!("expected {} vertices", counts[0]),
+            })?;
+            let p = numbers::<f32>(line, text, 3)?;
+            positions.push(Vec3::new(p[0], p[1], p[2]));
+        }
+
+        let mut faces = Vec::new();
+        for _ in 0..counts[1] {
+            let (line, text) = lines.next().ok_or(ImportError::Syntax {
+                line,
+                message: format!("expected {} faces", counts[1]),
+            })?;
+            let n = numbers::<usize>(line, text, 1)?[0];
+            let n = n.checked_add(1).ok_or_else(|| ImportError::Syntax {
+                line,
+                message: format!("face with {n} vertices"),
+            })?;
+            faces.push(numbers::<isize>(line, text, n)?[1..].to_vec());
+        }
+
+        Self::from_faces(positions, faces)
+    }
+
+    /// Wavefront OBJ, using only its vertices and faces
+    pub fn from_obj(contents: &str) -> Result<PolyGraph, ImportError> {
+        let mut positions = Vec::new();
+        let mut faces = Vec::new();
+        for (line, text) in significant_lines(contents, '#') {
+            let mut tokens = text.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let p = numbers::<f32>(line, &text[1..], 3)?;
+                    positions.push(Vec3::new(p[0], p[1], p[2]));
+                }
+                Some("f") => {
+                    let mut face = Vec::new();
+                    // Vertices can carry texture coordinates and normals, like `1/2/3`
+                    for token in tokens {
+                        let index = token.split('/').next().unwrap_or_default();
+                        let index = number::<isize>(line, index)?;
+                        // Counting starts at one, and negative numbers count back from the end
+                        face.push(if index < 0 {
+                            positions.len() as isize + index
+                        } else {
+                            index - 1
+                        });
+                    }
+                    faces.push(face);
+                }
+                _ => {}
+            }
+        }
+        Self::from_faces(positions, faces)
+    }
+
+    /// Build a polyhedron from faces given as indices into `positions`,
+    /// as long as they make up a closed, 3-connected surface with no holes through it
+    pub fn from_faces(
+        positions: Vec<Vec3>,
+        faces: Vec<Vec<isize>>,
+    ) -> Result<PolyGraph, ImportError> {
+        use ImportError::*;
+        if faces.is_empty() {
+            return Err(Empty);
+        }
+
+        let mut cycles = Vec::new();
+        for (i, face) in faces.into_iter().enumerate() {
+            let mut cycle = Vec::new();
+            for vertex in face {
+                if vertex < 0 || vertex as usize >= positions.len() {
+                    return Err(MissingVertex { face: i, vertex });
+                }
+                cycle.push(vertex as VertexId);
+            }
+            if cycle.len() < 3 || cycle.iter().collect::<HashSet<_>>().len() < cycle.len() {
+                return Err(DegenerateFace(i));
+            }
+            cycles.push(Face::new(cycle));
+        }
+
+        // Every edge has to border exactly two faces
+        let mut borders: HashMap<Edge, usize> = Default::default();
+        for cycle in cycles.iter() {
+            for e in cycle.edges() {
+                *borders.entry(e).or_default() += 1;
+            }
+        }
+        let mut edges = borders.into_iter().collect::<Vec<_>>();
+        edges.sort_by_key(|(e, _)| *e);
+        for &(e, count) in edges.iter() {
+            match count {
+                1 => return Err(Open(e.v(), e.u())),
+                2 => {}
+                _ => return Err(NonManifold(e.v(), e.u())),
+            }
+        }
+        let edges: HashSet<Edge> = edges.into_iter().map(|(e, _)| e).collect();
+
+        let vertices: HashSet<VertexId> = cycles.iter().flat_map(|c| c.iter().copied()).collect();
+        let mut adjacency: HashMap<VertexId, Vec<VertexId>> = Default::default();
+        for e in edges.iter() {
+            adjacency.entry(e.v()).or_default().push(e.u());
+            adjacency.entry(e.u()).or_default().push(e.v());
+        }
+        for neighbors in adjacency.values_mut() {
+            neighbors.sort();
+        }
+
+        if let Err(separator) = separator(&adjacency, None) {
+            return Err(separator.map_or(Disconnected, |v| NotThreeConnected(vec![v])));
+        }
+        let euler = vertices.len() as i64 - edges.len() as i64 + cycles.len() as i64;
+        if euler != 2 {
+            return Err(EulerCharacteristic(euler));
+        }
+        if vertices.len() < 4 {
+            return Err(NotThreeConnected(vec![]));
+        }
+        let mut sorted = vertices.iter().copied().collect::<Vec<_>>();
+        sorted.sort();
+        for &v in sorted.iter() {
+            if let Err(u) = separator(&adjacency, Some(v)) {
+                let mut separator = vec![v];
+                separator.extend(u);
+                return Err(NotThreeConnected(separator));
+            }
+        }
+
+        // Centered on the origin, and about as big as the presets
+        let center =
+            sorted.iter().fold(Vec3::zero(), |a, &v| a + positions[v]) / sorted.len() as f32;
+        let radius = sorted
+            .iter()
+            .map(|&v| (positions[v] - center).mag())
+            .fold(0.0, f32::max);
+        let scale = if radius > 0.0 { 1.0 / radius } else { 1.0 };
+
+        // Vertices are renumbered from zero, skipping any which no face uses
+        let ids: HashMap<VertexId, VertexId> =
+            sorted.iter().enumerate().map(|(id, &v)| (v, id)).collect();
+        let cycles = cycles
+            .into_iter()
+            .map(|cycle| cycle.into_iter().map(|v| ids[&v]).collect())
+            .collect();
+        // The checks above leave only a vertex pinched between two rings of faces
+        let mut graph = PolyGraph::from_cycles(cycles).map_err(|error| match error {
+            PolyGraphError::OpenVertex(v) => NotThreeConnected(vec![sorted[v]]),
+            _ => Disconnected,
+        })?;
+        graph.positions = sorted
+            .iter()
+            .map(|&v| (ids[&v], (positions[v] - center) * scale))
+            .collect();
+        graph.speeds = (0..sorted.len()).map(|v| (v, Vec3::zero())).collect();
+        // Files can wind their faces either way around
+        graph.orient_outward();
+        graph.sort_faces();
+        graph.springs();
+        Ok(graph)
+    }
+}
+
+/// Lines that aren't empty or comments, numbered from one
+fn significant_lines(contents: &str, comment: char) -> impl Iterator<Item = (usize, &str)> {
+    contents
+        .lines()
+        .enumerate()
+        .map(move |(i, line)| (i + 1, line.split(comment).next().unwrap_or_default().trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+fn number<T: FromStr>(line: usize, text: &str) -> Result<T, ImportError> {
+    text.parse().map_err(|_| ImportError::Syntax {
+        line,
+        message: format!("'{text}' is not a valid number"),
+    })
+}
+
+/// The first `count` numbers on a line, ignoring any after them
+fn numbers<T: FromStr>(line: usize, text: &str, count: usize) -> Result<Vec<T>, ImportError> {
+    let numbers = text
+        .split_whitespace()
+        .take(count)
+        .map(|n| number(line, n))
+        .collect::<Result<Vec<T>, _>>()?;
+    if numbers.len() < count {
+        return Err(ImportError::Syntax {
+            line,
+            message: format!("expected {count} numbers"),
+        });
+    }
+    Ok(numbers)
+}
+
+/// Check that the graph stays in one piece without `removed`, using Tarjan's lowpoints.
+/// Fails with a cut vertex if there is one, or with nothing if it is already in pieces.
+fn separator(
+    adjacency: &HashMap<VertexId, Vec<VertexId>>,
+    removed: Option<VertexId>,
+) -> Result<(), Option<VertexId>> {
+    let Some(&start) = adjacency.keys().filter(|&&v| Some(v) != removed).min() else {
+        return Ok(());
+    };
+    let mut discovered: HashMap<VertexId, usize> = Default::default();
+    let mut low: HashMap<VertexId, usize> = Default::default();
+    discovered.insert(start, 0);
+    low.insert(start, 0);
+    let mut root_children = 0;
+    // Depth first, remembering how far through its neighbors each vertex is
+    let mut stack = vec![(start, start, 0)];
+    while let Some((v, parent, i)) = stack.last().copied() {
+        if let Some(&w) = adjacency[&v].get(i) {
+            stack.last_mut().unwrap().2 += 1;
+            if Some(w) == removed || w == parent {
+                continue;
+            }
+            if let Some(&d) = discovered.get(&w) {
+                low.insert(v, low[&v].min(d));
+            } else {
+                discovered.insert(w, discovered.len());
+                low.insert(w, discovered[&w]);
+                if v == start {
+                    root_children += 1;
+                }
+                stack.push((w, v, 0));
+            }
+        } else {
+            stack.pop();
+            if v != start {
+                low.insert(parent, low[&parent].min(low[&v]));
+                if parent != start && low[&v] >= discovered[&parent] {
+                    return Err(Some(parent));
+                }
+            }
+        }
+    }
+    if root_children > 1 {
+        return Err(Some(start));
+    }
+    if discovered.len() < adjacency.len() - removed.is_some() as usize {
+        return Err(None);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bones::{ImportError, PolyGraph},
+        render::message::ConwayMessage,
+    };
+    use test_case::test_case;
+
+    const CUBE_OFF: &str = "OFF
+# A cube
+8 6 12
+-1 -1 -1
+1 -1 -1
+1 1 -1
+-1 1 -1
+-1 -1 1
+1 -1 1
+1 1 1
+-1 1 1
+4 0 3 2 1
+4 4 5 6 7
+4 0 1 5 4
+4 1 2 6 5
+4 2 3 7 6
+4 3 0 4 7 255 0 0
+";
+
+    const TETRAHEDRON_OBJ: &str = "# A tetrahedron
+o tetrahedron
+v 1 1 1
+v 1 -1 -1
+v -1 1 -1
+v -1 -1 1
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1
+f 1 4 2
+f -4 -1 -2
+f 2 4 3
+";
+
+    #[test]
+    fn off() {
+        let graph = PolyGraph::from_off(CUBE_OFF).unwrap();
+        assert_eq!(graph.vertices.len(), 8);
+        assert_eq!(graph.edges.len(), 12);
+        assert_eq!(graph.cycles.len(), 6);
+        assert_eq!(
+            graph.fingerprint(),
+            "C".parse::<PolyGraph>().unwrap().fingerprint()
+        );
+    }
+
+    /// Faces wound inward in the file are turned around
+    #[test]
+    fn outward() {
+        let inward = CUBE_OFF
+            .lines()
+            .map(
+                |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["4", a, b, c, d, ..] => format!("4 {d} {c} {b} {a}"),
+                    _ => line.to_string(),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join("\n");
+        for contents in [CUBE_OFF, inward.as_str()] {
+            let graph = PolyGraph::from_off(contents).unwrap();
+            assert!(graph.volume() > 0.0, "{}", graph.volume());
+        }
+    }
+
+    #[test]
+    fn obj() {
+        let graph = PolyGraph::from_obj(TETRAHEDRON_OBJ).unwrap();
+        assert_eq!(
+            graph.fingerprint(),
+            "T".parse::<PolyGraph>().unwrap().fingerprint()
+        );
+    }
+
+    /// Exported polyhedra come back the same shape, and can be operated on
+    #[test_case("tT"; "tT")]
+    #[test_case("gC"; "gC")]
+    #[test_case("jA5"; "jA5")]
+    fn round_trip(notation: &str) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        let (positions, faces) = graph.mesh();
+        let faces = faces
+            .into_iter()
+            .map(|face| face.into_iter().map(|v| v as isize).collect())
+            .collect();
+        let mut imported = PolyGraph::from_faces(positions, faces).unwrap();
+        assert_eq!(imported.fingerprint(), graph.fingerprint());
+
+        for conway in [
+            ConwayMessage::Kis,
+            ConwayMessage::Dual,
+            ConwayMessage::Truncate,
+        ] {
+            imported.operate(&conway).unwrap();
+        }
+        let operated: PolyGraph = format!("tdk{notation}").parse().unwrap();
+        assert_eq!(imported.fingerprint(), operated.fingerprint());
+    }
+
+    #[test_case("OFF\n1 1 0\n0 0 0\n", ImportError::Syntax { line: 2, message: "expected 1 faces".into() }; "missing faces")]
+    #[test_case("OFF\n3 1 0\n0 0 0\n1 0 0\n0 x 0\n3 0 1 2", ImportError::Syntax { line: 5, message: "'x' is not a valid number".into() }; "bad number")]
+    #[test_case("OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3", ImportError::MissingVertex { face: 0, vertex: 3 }; "missing vertex")]
+    #[test_case("OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 1", ImportError::DegenerateFace(0); "degenerate")]
+    #[test_case("OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n18446744073709551615 0 1 2", ImportError::Syntax { line: 6, message: "face with 18446744073709551615 vertices".into() }; "too many sides")]
+    #[test_case("OFF\n4 1 0\n0 0 0\n1 0 0\n0 1 0\n0 0 1\n3 0 1 2", ImportError::Open(0, 1); "open")]
+    #[test_case("OFF\n4 2 0\n0 0 0\n1 0 0\n1 1 0\n0 1 0\n4 0 1 2 3\n4 3 2 1 0", ImportError::NotThreeConnected(vec![0, 2]); "two squares")]
+    #[test_case("", ImportError::Empty; "empty")]
+    fn errors(contents: &str, error: ImportError) {
+        assert_eq!(PolyGraph::from_off(contents).unwrap_err(), error);
+    }
+
+    #[test]
+    fn torus() {
+        // Four by four grid of squares, wrapped around in both directions
+        let mut faces = Vec::new();
+        for i in 0..4 {
+            for j in 0..4 {
+                let v = |a: isize, b: isize| (a % 4) * 4 + (b % 4);
+                faces.push(vec![v(i, j), v(i + 1, j), v(i + 1, j + 1), v(i, j + 1)]);
+            }
+        }
+        let positions = vec![Default::default(); 16];
+        assert_eq!(
+            PolyGraph::from_faces(positions, faces).unwrap_err(),
+            ImportError::EulerCharacteristic(0)
+        );
+    }
+
+    #[test]
+    fn non_manifold() {
+        let faces = vec![vec![0, 1, 2], vec![0, 2, 1], vec![0, 1, 3]];
+        let positions = vec![Default::default(); 4];
+        assert_eq!(
+            PolyGraph::from_faces(positions, faces).unwrap_err(),
+            ImportError::NonManifold(0, 1)
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::bones::PolyGraph;
+use std::{f32::consts::PI, fmt::Write as _};
+use ultraviolet::Vec3;
+
+/// Dihedral angles closer than this, in radians, count as the same angle
+const ANGLE_TOLERANCE: f32 = 1e-3;
+
+/// Sizes and angles of the polyhedron as it is currently laid out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Measurements {
+    pub volume: f32,
+    /// Total area of every face
+    pub area: f32,
+    /// Area of each face, in the order of `cycles`
+    pub face_areas: Vec<f32>,
+    /// Furthest any vertex is from the plane of its face
+    pub planarity: f32,
+    /// Every different angle between neighbouring faces, in radians from smallest to largest
+    pub dihedral_angles: Vec<f32>,
+    pub shortest_edge: f32,
+    pub longest_edge: f32,
+    pub mean_edge: f32,
+    /// Mean distance from the centre to the vertices
+    pub circumradius: f32,
+    /// Mean distance from the centre to the lines through the edges
+    pub midradius: f32,
+    /// Mean distance from the centre to the planes of the faces
+    pub inradius: f32,
+}
+
+impl Measurements {
+    pub fn min_dihedral(&self) -> f32 {
+        self.dihedral_angles.first().copied().unwrap_or_default()
+    }
+
+    pub fn max_dihedral(&self) -> f32 {
+        self.dihedral_angles.last().copied().unwrap_or_default()
+    }
+
+    /// Two columns of names and values, with angles in degrees
+    pub fn csv(&self) -> String {
+        let mut csv = String::from("measurement,value\n");
+        let mut row = |name: &str, value: f32| {
+            let _ = writeln!(csv, "{name},{value}");
+        };
+        row("volume", self.volume);
+        row("area", self.area);
+        row("planarity", self.planarity);
+        row("shortest edge", self.shortest_edge);
+        row("longest edge", self.longest_edge);
+        row("mean edge", self.mean_edge);
+        row("circumradius", self.circumradius);
+        row("midradius", self.midradius);
+        row("inradius", self.inradius);
+        for angle in self.dihedral_angles.iter() {
+            row("dihedral angle", angle.to_degrees());
+        }
+        for (i, area) in self.face_areas.iter().enumerate() {
+            row(&format!("face {i} area"), *area);
+        }
+        csv
+    }
+}
+
+impl PolyGraph {
+    /// Volume enclosed by the faces, negative when they wind clockwise from outside
+    pub fn volume(&self) -> f32 {
+        let position = |v| self.positions.get(v).copied().unwrap_or_default();
+        self.cycles
+            .iter()
+            .flat_map(|cycle| {
+                (1..cycle.len().saturating_sub(1)).map(move |i| {
+                    let (a, b, c) = (&cycle[0], &cycle[i], &cycle[i + 1]);
+                    position(a).dot(position(b).cross(position(c)))
+                })
+            })
+            .sum::<f32>()
+            / 6.0
+    }
+
+    /// Area of a face, projected onto its own plane when it isn't flat
+    pub fn face_area(&self, face_index: usize) -> f32 {
+        let positions = self.face_positions(face_index);
+        let n = positions.len();
+        (0..n)
+            .map(|i| positions[i].cross(positions[(i + 1) % n]))
+            .fold(Vec3::zero(), |a, b| a + b)
+            .mag()
+            / 2.0
+    }
+
+    /// Angle inside the polyhedron between the two faces on each edge, over a half turn where
+    /// the surface folds back in. Faces have to be oriented.
+    pub fn dihedral_angles(&self) -> Vec<f32> {
+        let across = self.across();
+        let normals = (0..self.cycles.len())
+            .map(|i| self.face_normal(i))
+            .collect::<Vec<_>>();
+        self.cycles
+            .iter()
+            .enumerate()
+            .flat_map(|(i, cycle)| {
+                let n = cycle.len();
+                (0..n).map(move |j| (i, cycle[j], cycle[(j + 1) % n]))
+            })
+            .filter(|&(_, a, b)| a < b)
+            .filter_map(|(i, a, b)| {
+                let other = normals[*across.get(&(a, b))?];
+                let normal = normals[i];
+                let along = (self.positions[&b] - self.positions[&a]).normalized();
+                // How far the normal turns crossing the edge, negative where it folds back in
+                let turn = normal.cross(other).dot(along).atan2(normal.dot(other));
+                Some(PI - turn)
+            })
+            .collect()
+    }
+
+    /// Measure the polyhedron where it is now, from the centroid of its vertices
+    pub fn measure(&self) -> Measurements {
+        let center = self.positions.values().fold(Vec3::zero(), |a, &b| a + b)
+            / self.positions.len().max(1) as f32;
+        let mean = |values: &mut dyn Iterator<Item = f32>| {
+            let (sum, count) = values.fold((0.0, 0), |(sum, count), x| (sum + x, count + 1));
+            sum / count.max(1) as f32
+        };
+
+        let face_areas = (0..self.cycles.len())
+            .map(|i| self.face_area(i))
+            .collect::<Vec<_>>();
+        let planarity = (0..self.cycles.len())
+            .flat_map(|i| {
+                let (normal, centroid) = (self.face_normal(i), self.face_centroid(i));
+                self.face_positions(i)
+                    .into_iter()
+                    .map(move |p| normal.dot(p - centroid).abs())
+            })
+            .fold(0.0, f32::max);
+        let inradius = mean(&mut (0..self.cycles.len()).map(|i| {
+            self.face_normal(i)
+                .dot(self.face_centroid(i) - center)
+                .abs()
+        }));
+
+        let mut dihedral_angles = self.dihedral_angles();
+        dihedral_angles.sort_by(f32::total_cmp);
+        dihedral_angles.dedup_by(|a, b| *a - *b < ANGLE_TOLERANCE);
+
+        let ends = self
+            .edges
+            .iter()
+            .map(|e| (self.positions[&e.v()], self.positions[&e.u()]))
+            .collect::<Vec<_>>();
+        let lengths = ends
+            .iter()
+            .map(|(a, b)| (*b - *a).mag())
+            .collect::<Vec<_>>();
+        let midradius = mean(&mut ends.iter().map(|&(a, b)| {
+            // Closest point to the centre on the line through the edge
+            let along = (b - a).normalized();
+            let offset = (center - a) - along * along.dot(center - a);
+            offset.mag()
+        }));
+
+        Measurements {
+            volume: self.volume(),
+            area: face_areas.iter().sum(),
+            face_areas,
+            planarity,
+            dihedral_angles,
+            shortest_edge: lengths.iter().copied().reduce(f32::min).unwrap_or_default(),
+            longest_edge: lengths.iter().copied().reduce(f32::max).unwrap_or_default(),
+            mean_edge: mean(&mut lengths.iter().copied()),
+            circumradius: mean(&mut self.positions.values().map(|&p| (p - center).mag())),
+            midradius,
+            inradius,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bones::{settled, PolyGraph, CANONICAL_TOLERANCE};
+    use test_case::test_case;
+
+    /// Unit edges, flat faces and a circumradius that grows with the number of sides
+    fn prism(n: usize) -> PolyGraph {
+        let mut graph = PolyGraph::prism(n);
+        let radius = 0.5 / (std::f32::consts::PI / n as f32).sin();
+        for i in 0..n {
+            let angle = 2.0 * std::f32::consts::PI * i as f32 / n as f32;
+            let (x, z) = (radius * angle.cos(), radius * angle.sin());
+            graph
+                .positions
+                .insert(i, ultraviolet::Vec3::new(x, -0.5, z));
+            graph
+                .positions
+                .insert(i + n, ultraviolet::Vec3::new(x, 0.5, z));
+        }
+        graph.orient_outward();
+        graph
+    }
+
+    #[test]
+    fn cube() {
+        let m = prism(4).measure();
+        assert!((m.volume - 1.0).abs() < 1e-5);
+        assert!((m.area - 6.0).abs() < 1e-5);
+        assert!(m.face_areas.iter().all(|a| (a - 1.0).abs() < 1e-5));
+        assert!(m.planarity < 1e-5);
+        assert_eq!(m.dihedral_angles.len(), 1);
+        assert!((m.max_dihedral().to_degrees() - 90.0).abs() < 1e-3);
+        assert!((m.shortest_edge - 1.0).abs() < 1e-5 && (m.longest_edge - 1.0).abs() < 1e-5);
+        assert!((m.circumradius - 3f32.sqrt() / 2.0).abs() < 1e-5);
+        assert!((m.midradius - 2f32.sqrt() / 2.0).abs() < 1e-5);
+        assert!((m.inradius - 0.5).abs() < 1e-5);
+        assert_eq!(m.csv().lines().count(), 1 + 9 + 1 + 6);
+    }
+
+    /// Hexagonal prism: a triangular and a rectangular kind of dihedral angle
+    #[test]
+    fn hexagonal_prism() {
+        let m = prism(6).measure();
+        let hexagon = 3.0 * 3f32.sqrt() / 2.0;
+        assert!((m.volume - hexagon).abs() < 1e-4);
+        assert!((m.area - 2.0 * hexagon - 6.0).abs() < 1e-4);
+        let degrees = m
+            .dihedral_angles
+            .iter()
+            .map(|a| a.to_degrees().round())
+            .collect::<Vec<_>>();
+        assert_eq!(degrees, [90.0, 120.0]);
+    }
+
+    /// Every face of the canonical form is flat, and its edges touch the unit sphere
+    #[test_case("C"; "C")]
+    #[test_case("tO"; "tO")]
+    #[test_case("aD"; "aD")]
+    fn canonical(notation: &str) {
+        let mut graph = settled(notation);
+        graph.canonicalize(CANONICAL_TOLERANCE, 2000);
+        let m = graph.measure();
+        assert!(m.planarity < 1e-3);
+        assert!((m.midradius - 1.0).abs() < 1e-2);
+        assert!(m.inradius < m.midradius && m.midradius < m.circumradius);
+        assert!(m.volume > 0.0);
+        assert!(m
+            .dihedral_angles
+            .iter()
+            .all(|&a| a > 0.0 && a < std::f32::consts::PI));
+    }
+}
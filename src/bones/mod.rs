@@ -1,12 +1,39 @@
+mod algebra;
+mod barnes_hut;
+mod canonical;
+mod coloring;
 mod conway;
+mod distance;
 mod edge;
+mod error;
+mod export;
 mod face;
+mod fingerprint;
+mod flag;
+mod halfedge;
+mod import;
+mod measure;
+mod net;
+mod notation;
 mod platonic;
 mod polygraph;
 mod polyhedron;
+mod provenance;
+mod symmetry;
 mod transaction;
+mod validate;
 
+pub use canonical::*;
 pub use edge::*;
+pub use error::*;
 pub use face::*;
+pub use fingerprint::*;
+pub use flag::*;
+pub use halfedge::*;
+pub use import::*;
+pub use net::*;
+pub use notation::*;
 pub use polygraph::*;
+pub use provenance::*;
+pub use symmetry::*;
 pub use transaction::*;
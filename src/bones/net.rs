@@ -0,0 +1,482 @@
+use crate::{
+    bones::{export::Mesh, Edge, PolyGraph, CANONICAL_TOLERANCE},
+    render::color::RGBA,
+};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use std::{collections::VecDeque, fmt::Write as _};
+use ultraviolet::{Mat4, Vec2, Vec3};
+
+/// Iterations of the canonical form solver used to flatten the faces before unfolding
+const FLATTEN_ITERATIONS: usize = 2000;
+/// Average printed edge length, in millimetres
+const EDGE_MM: f32 = 30.0;
+/// Space around and between pieces, in millimetres
+const MARGIN_MM: f32 = 10.0;
+
+/// A face laid flat, with corners in the same order as its face in the mesh
+#[derive(Debug, Clone)]
+pub struct NetFace {
+    /// Index of the face in the mesh
+    pub face: usize,
+    /// Corners, counterclockwise when seen from the outside of the polyhedron
+    pub corners: Vec<Vec2>,
+    /// Mesh vertex at each corner
+    pub vertices: Vec<usize>,
+    /// Face of the same piece this one folds against, or `None` for the first face laid
+    pub parent: Option<usize>,
+}
+
+/// Faces of a polyhedron unfolded into the plane
+#[derive(Debug, Clone, Default)]
+pub struct Net {
+    /// Groups of faces which each unfold from a single sheet without overlapping,
+    /// laid out side by side
+    pub pieces: Vec<Vec<NetFace>>,
+    /// Edges which are folded, every other edge is cut
+    pub folds: HashSet<Edge>,
+}
+
+// Unfolding
+impl PolyGraph {
+    /// Unfold the canonical form of the polyhedron, so that every face is flat
+    pub fn net(&self) -> Net {
+        let mut graph = self.clone();
+        graph.canonicalize(CANONICAL_TOLERANCE, FLATTEN_ITERATIONS);
+        Net::new(&graph.mesh())
+    }
+
+    /// Corners of every face with the net opened by `amount`, from closed at zero to flat at one.
+    /// Each face turns about the edge it shares with its parent, carrying its children with it.
+    pub fn unfolded(&self, net: &Net, amount: f32) -> Vec<Vec<Vec3>> {
+        let faces = (0..self.cycles.len())
+            .map(|i| self.face_positions(i))
+            .collect::<Vec<_>>();
+        let normals = (0..self.cycles.len())
+            .map(|i| self.face_normal(i))
+            .collect::<Vec<_>>();
+
+        let mut transforms = vec![Mat4::identity(); faces.len()];
+        for piece in net.pieces.iter() {
+            // Parents are always laid before their children
+            for face in piece.iter() {
+                let Some(parent) = face.parent.map(|parent| piece[parent].face) else {
+                    continue;
+                };
+                let (child, parent_cycle) = (&self.cycles[face.face], &self.cycles[parent]);
+                let hinge = (0..child.len())
+                    .map(|i| (child[i], child[(i + 1) % child.len()]))
+                    .find(|(a, b)| parent_cycle.containz(a) && parent_cycle.containz(b));
+                let (Some((a, b)), Some(&transform)) = (hinge, transforms.get(parent)) else {
+                    continue;
+                };
+                let (a, b) = (self.positions[&a], self.positions[&b]);
+                let axis = (b - a).normalized();
+                let (n, m) = (normals[face.face], normals[parent]);
+                let angle = n.cross(m).dot(axis).atan2(n.dot(m));
+                transforms[face.face] = transform
+                    * Mat4::from_translation(a)
+                    * Mat4::from_rotation_around(axis.into_homogeneous_vector(), angle * amount)
+                    * Mat4::from_translation(-a);
+            }
+        }
+
+        let count = faces.iter().map(Vec::len).sum::<usize>().max(1) as f32;
+        let closed = faces.iter().flatten().fold(Vec3::zero(), |a, &b| a + b) / count;
+        let mut unfolded = faces
+            .into_iter()
+            .zip(transforms)
+            .map(|(face, transform)| {
+                face.into_iter()
+                    .map(|p| transform.transform_point3(p))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        // Keep the net centered where the polyhedron was
+        let shift = unfolded.iter().flatten().fold(Vec3::zero(), |a, &b| a + b) / count - closed;
+        for p in unfolded.iter_mut().flatten() {
+            *p -= shift;
+        }
+        unfolded
+    }
+}
+
+impl Net {
+    /// Number of faces laid out, across every piece
+    pub fn faces(&self) -> usize {
+        self.pieces.iter().map(Vec::len).sum()
+    }
+
+    /// Grow a spanning tree of the dual breadth first from the largest face,
+    /// skipping any face which would overlap those already laid out.
+    /// Faces which can't be reached without overlapping start a new piece.
+    pub fn new((positions, faces): &Mesh) -> Net {
+        let flat = faces
+            .iter()
+            .map(|face| flatten(&face.iter().map(|&v| positions[v]).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        let mut across: HashMap<Edge, Vec<usize>> = Default::default();
+        for (i, face) in faces.iter().enumerate() {
+            for j in 0..face.len() {
+                let e = Edge::from((face[j], face[(j + 1) % face.len()]));
+                across.entry(e).or_default().push(i);
+            }
+        }
+
+        let mut net = Net::default();
+        let mut placed = vec![false; faces.len()];
+        let mut order = (0..faces.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| usize::MAX - faces[i].len());
+        for root in order {
+            if placed[root] {
+                continue;
+            }
+            placed[root] = true;
+            let mut piece = vec![NetFace {
+                face: root,
+                corners: flat[root].clone(),
+                vertices: faces[root].clone(),
+                parent: None,
+            }];
+            let mut queue = VecDeque::from([0]);
+            while let Some(parent) = queue.pop_front() {
+                let n = piece[parent].vertices.len();
+                for j in 0..n {
+                    let (a, b) = (
+                        piece[parent].vertices[j],
+                        piece[parent].vertices[(j + 1) % n],
+                    );
+                    let e = Edge::from((a, b));
+                    let Some(&child) = across[&e].iter().find(|&&f| !placed[f]) else {
+                        continue;
+                    };
+                    // The child goes round the shared edge the other way,
+                    // which puts it on the other side of the edge once it's lined up
+                    let (pa, pb) = (piece[parent].corners[j], piece[parent].corners[(j + 1) % n]);
+                    let k = faces[child]
+                        .iter()
+                        .position(|&v| v == a)
+                        .unwrap_or_default();
+                    let m = faces[child].len();
+                    let (qa, qb) = (flat[child][k], flat[child][(k + m - 1) % m]);
+                    let corners = align(&flat[child], (qa, qb), (pa, pb));
+                    if piece.iter().any(|other| overlap(&other.corners, &corners)) {
+                        continue;
+                    }
+                    placed[child] = true;
+                    net.folds.insert(e);
+                    queue.push_back(piece.len());
+                    piece.push(NetFace {
+                        face: child,
+                        corners,
+                        vertices: faces[child].clone(),
+                        parent: Some(parent),
+                    });
+                }
+            }
+            net.pieces.push(piece);
+        }
+        net.arrange();
+        net
+    }
+
+    /// Move the pieces into a row, starting from the origin
+    fn arrange(&mut self) {
+        let mut x = 0.0;
+        for piece in self.pieces.iter_mut() {
+            let (min, max) = bounds(piece.iter().flat_map(|face| face.corners.iter()));
+            for face in piece.iter_mut() {
+                for corner in face.corners.iter_mut() {
+                    *corner += Vec2::new(x, 0.0) - min;
+                }
+            }
+            x += max.x - min.x;
+        }
+    }
+
+    /// Papercraft template in millimetres, printed side out.
+    /// Cut lines are solid and fold lines are dashed. With `tabs`,
+    /// one side of every cut edge gets a glue tab, folded along the edge.
+    pub fn svg(&self, colors: Option<&[RGBA]>, tabs: bool) -> String {
+        let faces = self.pieces.iter().flatten().collect::<Vec<_>>();
+        let lengths = faces
+            .iter()
+            .flat_map(|face| edges(&face.corners).map(|(a, b)| (b - a).mag()))
+            .collect::<Vec<_>>();
+        let mean = lengths.iter().sum::<f32>() / lengths.len().max(1) as f32;
+        let scale = if mean > 0.0 { EDGE_MM / mean } else { 1.0 };
+        // Pieces are spaced out by the margin, and flipped upright since SVG counts down
+        let mut offsets = Vec::new();
+        let mut x = MARGIN_MM;
+        for piece in self.pieces.iter() {
+            let (min, max) = bounds(piece.iter().flat_map(|face| face.corners.iter()));
+            offsets.push(x - min.x * scale);
+            x += (max.x - min.x) * scale + MARGIN_MM;
+        }
+        let (_, max) = bounds(faces.iter().flat_map(|face| face.corners.iter()));
+        let (width, height) = (x, max.y * scale + 2.0 * MARGIN_MM);
+        let point = |piece: usize, p: Vec2| {
+            format!(
+                "{:.3},{:.3}",
+                offsets[piece] + p.x * scale,
+                height - MARGIN_MM - p.y * scale
+            )
+        };
+
+        let mut svg = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.1}mm\" height=\"{height:.1}mm\" viewBox=\"0 0 {width:.3} {height:.3}\">"
+        );
+        svg.push_str("<style>.cut{fill:none;stroke:#000;stroke-width:0.3}.fold{fill:none;stroke:#000;stroke-width:0.2;stroke-dasharray:2 1}.tab{fill:#eee}</style>\n");
+
+        let mut cuts = String::new();
+        let mut folds = String::new();
+        let mut tab_polygons = String::new();
+        for (p, piece) in self.pieces.iter().enumerate() {
+            for face in piece.iter() {
+                let fill = colors
+                    .and_then(|colors| colors.get(face.face))
+                    .map(|c| format!("rgb({},{},{})", c.r, c.g, c.b))
+                    .unwrap_or("#fff".into());
+                let corners = face
+                    .corners
+                    .iter()
+                    .map(|&c| point(p, c))
+                    .collect::<Vec<_>>();
+                let _ = writeln!(
+                    svg,
+                    "<polygon points=\"{}\" fill=\"{fill}\"/>",
+                    corners.join(" ")
+                );
+
+                let n = face.vertices.len();
+                for (j, (a, b)) in edges(&face.corners).enumerate() {
+                    let (v, u) = (face.vertices[j], face.vertices[(j + 1) % n]);
+                    let line = format!("M{}L{}", point(p, a), point(p, b));
+                    if self.folds.contains(&Edge::from((v, u))) {
+                        // Folds are shared by two faces of the same piece, so only one draws it
+                        if v < u {
+                            folds.push_str(&line);
+                        }
+                    } else if tabs && v < u {
+                        // Counterclockwise faces have the outside on the right
+                        let along = b - a;
+                        let out = Vec2::new(along.y, -along.x) * 0.25;
+                        let tab = [a, a + along * 0.25 + out, b - along * 0.25 + out, b];
+                        let tab = tab.map(|c| point(p, c));
+                        let _ = writeln!(
+                            tab_polygons,
+                            "<polygon class=\"tab\" points=\"{}\"/>",
+                            tab.join(" ")
+                        );
+                        let _ = write!(cuts, "M{}L{}L{}L{}", tab[0], tab[1], tab[2], tab[3]);
+                        folds.push_str(&line);
+                    } else {
+                        cuts.push_str(&line);
+                    }
+                }
+            }
+        }
+        svg.push_str(&tab_polygons);
+        let _ = writeln!(svg, "<path class=\"cut\" d=\"{cuts}\"/>");
+        let _ = writeln!(svg, "<path class=\"fold\" d=\"{folds}\"/>");
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Consecutive pairs of corners, wrapping around
+fn edges(corners: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    (0..corners.len()).map(|i| (corners[i], corners[(i + 1) % corners.len()]))
+}
+
+fn bounds<'a>(corners: impl Iterator<Item = &'a Vec2>) -> (Vec2, Vec2) {
+    corners.fold(
+        (Vec2::broadcast(f32::MAX), Vec2::broadcast(f32::MIN)),
+        |(min, max), &c| (min.min_by_component(c), max.max_by_component(c)),
+    )
+}
+
+/// Unit normal of a face, pointing the way it winds counterclockwise around
+fn normal(face: &[Vec3]) -> Vec3 {
+    let n = face.len();
+    let normal = (0..n)
+        .map(|i| face[i].cross(face[(i + 1) % n]))
+        .fold(Vec3::zero(), |a, b| a + b);
+    if normal.mag_sq() == 0.0 {
+        normal
+    } else {
+        normal.normalized()
+    }
+}
+
+/// Project a face onto its own plane, keeping it counterclockwise around its normal
+fn flatten(face: &[Vec3]) -> Vec<Vec2> {
+    let n = face.len();
+    let centroid = face.iter().fold(Vec3::zero(), |a, &b| a + b) / n as f32;
+    let normal = normal(face);
+    let x = (face[0] - centroid).normalized();
+    let y = normal.cross(x);
+    face.iter()
+        .map(|&p| Vec2::new((p - centroid).dot(x), (p - centroid).dot(y)))
+        .collect()
+}
+
+/// Rotate and translate `corners` so that the edge `from` lies along `to`
+fn align(corners: &[Vec2], from: (Vec2, Vec2), to: (Vec2, Vec2)) -> Vec<Vec2> {
+    let angle = |(a, b): (Vec2, Vec2)| (b.y - a.y).atan2(b.x - a.x);
+    let (sin, cos) = (angle(to) - angle(from)).sin_cos();
+    let pivot = (from.0 + from.1) / 2.0;
+    let target = (to.0 + to.1) / 2.0;
+    corners
+        .iter()
+        .map(|&c| {
+            let d = c - pivot;
+            target + Vec2::new(d.x * cos - d.y * sin, d.x * sin + d.y * cos)
+        })
+        .collect()
+}
+
+/// Whether two polygons cover any of the same area.
+/// They are shrunk a little first, so that touching along an edge or at a corner doesn't count.
+fn overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    let (a_min, a_max) = bounds(a.iter());
+    let (b_min, b_max) = bounds(b.iter());
+    if a_max.x < b_min.x || b_max.x < a_min.x || a_max.y < b_min.y || b_max.y < a_min.y {
+        return false;
+    }
+    let shrink = |polygon: &[Vec2]| {
+        let centroid = polygon.iter().fold(Vec2::zero(), |a, &b| a + b) / polygon.len() as f32;
+        polygon
+            .iter()
+            .map(|&p| centroid + (p - centroid) * 0.999)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (shrink(a), shrink(b));
+    edges(&a).any(|e| edges(&b).any(|f| crosses(e, f)))
+        || a.iter().any(|&p| inside(p, &b))
+        || b.iter().any(|&p| inside(p, &a))
+}
+
+/// Whether two segments cross at a point inside both of them.
+/// Segments along the same line only touch, however rounding leaves them.
+fn crosses((a, b): (Vec2, Vec2), (c, d): (Vec2, Vec2)) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).x * (r - p).y - (q - p).y * (r - p).x;
+    let tolerance = 1e-6 * (b - a).mag_sq() * (d - c).mag_sq();
+    side(a, b, c) * side(a, b, d) < -tolerance && side(c, d, a) * side(c, d, b) < -tolerance
+}
+
+/// Even-odd test for whether a point is inside a polygon
+fn inside(p: Vec2, polygon: &[Vec2]) -> bool {
+    edges(polygon)
+        .filter(|(a, b)| (a.y > p.y) != (b.y > p.y))
+        .filter(|(a, b)| p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x))
+        .count()
+        % 2
+        == 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bones::settled;
+    use test_case::test_case;
+
+    fn net(notation: &str) -> (Mesh, Net) {
+        let mut graph = settled(notation);
+        graph.canonicalize(CANONICAL_TOLERANCE, FLATTEN_ITERATIONS);
+        let mesh = graph.mesh();
+        let net = Net::new(&mesh);
+        (mesh, net)
+    }
+
+    #[test_case("C"; "C")]
+    #[test_case("tI"; "tI")]
+    #[test_case("gC"; "gC")]
+    #[test_case("P7"; "P7")]
+    #[test_case("kD"; "kD")]
+    fn unfold(notation: &str) {
+        let (mesh, net) = net(notation);
+        let (positions, faces) = mesh;
+
+        // Every face appears once, and each piece is held together by its folds
+        assert_eq!(net.faces(), faces.len());
+        assert_eq!(net.folds.len(), faces.len() - net.pieces.len());
+
+        let laid = net.pieces.iter().flatten().collect::<Vec<_>>();
+        for (i, face) in laid.iter().enumerate() {
+            // Edges keep their lengths
+            for (j, (a, b)) in edges(&face.corners).enumerate() {
+                let n = face.vertices.len();
+                let (v, u) = (face.vertices[j], face.vertices[(j + 1) % n]);
+                let length = (positions[v] - positions[u]).mag();
+                assert!(((b - a).mag() - length).abs() < 1e-2);
+            }
+            // And nothing overlaps
+            for other in laid[i + 1..].iter() {
+                assert!(!overlap(&face.corners, &other.corners));
+            }
+        }
+    }
+
+    #[test_case("C"; "C")]
+    #[test_case("tI"; "tI")]
+    #[test_case("gC"; "gC")]
+    fn fold(notation: &str) {
+        let mut graph = settled(notation);
+        graph.canonicalize(CANONICAL_TOLERANCE, FLATTEN_ITERATIONS);
+        let net = Net::new(&graph.mesh());
+        assert_eq!(net.pieces.len(), 1);
+
+        // Closed is where the polyhedron already is
+        let closed = graph.unfolded(&net, 0.0);
+        for (i, face) in closed.iter().enumerate() {
+            for (&p, q) in face.iter().zip(graph.face_positions(i)) {
+                assert!((p - q).mag() < 1e-4);
+            }
+        }
+
+        // And flat is the same shape as the net, up to where it lies
+        let flat = graph.unfolded(&net, 1.0);
+        let mut vertices = graph.vertices.iter().copied().collect::<Vec<_>>();
+        vertices.sort();
+        let corners = net.pieces[0]
+            .iter()
+            .map(|face| {
+                let v = vertices[face.vertices[0]];
+                let i = graph.cycles[face.face]
+                    .iter()
+                    .position(|&u| u == v)
+                    .unwrap();
+                (face.corners[0], flat[face.face][i])
+            })
+            .collect::<Vec<_>>();
+        for &(a, p) in corners.iter() {
+            for &(b, q) in corners.iter() {
+                assert!(((a - b).mag() - (p - q).mag()).abs() < 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn svg() {
+        let (_, net) = net("C");
+        assert_eq!(net.pieces.len(), 1);
+        let colors = [RGBA::new(255, 0, 0, 255); 6];
+
+        // Seven cut edges and five folds on a cube
+        let svg = net.svg(Some(&colors), false);
+        assert_eq!(svg.matches("fill=\"rgb(255,0,0)\"").count(), 6);
+        let cuts = svg.lines().find(|l| l.contains("class=\"cut\"")).unwrap();
+        let folds = svg.lines().find(|l| l.contains("class=\"fold\"")).unwrap();
+        assert_eq!(cuts.matches('M').count(), 14);
+        assert_eq!(folds.matches('M').count(), 5);
+
+        let svg = net.svg(None, true);
+        assert_eq!(svg.matches("class=\"tab\"").count(), 7);
+        let cuts = svg.lines().find(|l| l.contains("class=\"cut\"")).unwrap();
+        assert_eq!(cuts.matches('M').count(), 14);
+        let folds = svg.lines().find(|l| l.contains("class=\"fold\"")).unwrap();
+        assert_eq!(folds.matches('M').count(), 12);
+    }
+}
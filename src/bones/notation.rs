@@ -0,0 +1,261 @@
+use crate::{
+    bones::{PolyGraph, PolyGraphError, Transaction},
+    render::message::{ConwayMessage, PresetMessage},
+};
+use std::{fmt::Display, str::FromStr};
+use strum::IntoEnumIterator;
+
+/// Conway Polyhedron Notation, e.g. `tkD`
+#[derive(Debug, Clone)]
+pub struct Notation {
+    /// Operations in the order they are written, so the last one is applied first
+    pub operations: Vec<ConwayMessage>,
+    /// Polyhedron the operations are applied to
+    pub seed: PresetMessage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationError {
+    /// Nothing to parse
+    Empty,
+    /// Operations were never followed by a seed
+    MissingSeed,
+    /// Neither an operation nor a seed
+    UnknownSymbol { symbol: char, position: usize },
+    /// `P`, `A` and `Y` seeds need a number of sides, three or more
+    SeedArgument { seed: char, argument: String },
+    /// `GC` needs two numbers which are not both zero
+    OperatorArgument { operator: String, argument: String },
+    /// An operation couldn't be applied to the polyhedron it was given
+    Operation(PolyGraphError),
+}
+
+impl Display for NotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use NotationError::*;
+        match self {
+            Empty => f.write_str("empty notation"),
+            MissingSeed => f.write_str("no seed polyhedron"),
+            UnknownSymbol { symbol, position } => {
+                f.write_fmt(format_args!("unknown symbol '{symbol}' at {position}"))
+            }
+            SeedArgument { seed, argument } => f.write_fmt(format_args!(
+                "'{argument}' is not a valid number of sides for '{seed}'"
+            )),
+            OperatorArgument { operator, argument } => f.write_fmt(format_args!(
+                "'{argument}' is not a valid argument for '{operator}'"
+            )),
+            Operation(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+impl From<PolyGraphError> for NotationError {
+    fn from(error: PolyGraphError) -> Self {
+        NotationError::Operation(error)
+    }
+}
+
+impl FromStr for Notation {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(NotationError::Empty);
+        }
+
+        let mut operations = Vec::new();
+        let mut position = 0;
+        while let Some(symbol) = s[position..].chars().next() {
+            // Operations can be written with more than one character, like `L0`
+            if let Some(conway) = ConwayMessage::iter()
+                .filter(|conway| !matches!(conway, ConwayMessage::GoldbergCoxeter(..)))
+                .find(|conway| s[position..].starts_with(&conway.notation()))
+            {
+                position += conway.notation().len();
+                operations.push(conway);
+                continue;
+            }
+
+            if let Some(rest) = s[position..].strip_prefix("GC(") {
+                let (m, n) = rest
+                    .split_once(')')
+                    .and_then(|(arguments, _)| arguments.split_once(','))
+                    .and_then(|(m, n)| Some((m.trim().parse().ok()?, n.trim().parse().ok()?)))
+                    .filter(|&(m, n): &(usize, usize)| m + n > 0)
+                    .ok_or(NotationError::OperatorArgument {
+                        operator: "GC".into(),
+                        argument: rest.split(')').next().unwrap_or_default().to_string(),
+                    })?;
+                operations.push(ConwayMessage::GoldbergCoxeter(m, n));
+                position += "GC(".len() + rest.find(')').unwrap_or_default() + 1;
+                continue;
+            }
+
+            // Everything after the first non operation is the seed
+            let argument = &s[position + symbol.len_utf8()..];
+            let seed = match symbol {
+                'P' | 'A' | 'Y' => {
+                    let sides = argument.parse::<usize>().ok().filter(|&n| n >= 3).ok_or(
+                        NotationError::SeedArgument {
+                            seed: symbol,
+                            argument: argument.to_string(),
+                        },
+                    )?;
+                    match symbol {
+                        'P' => PresetMessage::Prism(sides),
+                        'A' => PresetMessage::AntiPrism(sides),
+                        _ => PresetMessage::Pyramid(sides),
+                    }
+                }
+                _ => {
+                    let seed = PresetMessage::try_from(symbol)
+                        .map_err(|symbol| NotationError::UnknownSymbol { symbol, position })?;
+                    // Platonic seeds don't take arguments
+                    if let Some((offset, symbol)) = argument.char_indices().next() {
+                        return Err(NotationError::UnknownSymbol {
+                            symbol,
+                            position: position + 1 + offset,
+                        });
+                    }
+                    seed
+                }
+            };
+
+            return Ok(Self { operations, seed });
+        }
+
+        Err(NotationError::MissingSeed)
+    }
+}
+
+impl Display for Notation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for conway in self.operations.iter() {
+            f.write_str(&conway.notation())?;
+        }
+        f.write_str(&self.seed.notation())
+    }
+}
+
+impl Notation {
+    /// Seed polyhedron with no operations applied
+    pub fn seed(&self) -> PolyGraph {
+        PolyGraph::preset(&self.seed)
+    }
+
+    /// Transactions applying each operation from right to left
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.operations
+            .iter()
+            .rev()
+            .cloned()
+            .map(Transaction::Conway)
+            .collect()
+    }
+
+    /// Seed polyhedron which will animate through every operation
+    pub fn animated(&self) -> PolyGraph {
+        let mut polyhedron = self.seed();
+        polyhedron.transactions = self.transactions();
+        polyhedron
+    }
+
+    /// Final polyhedron, with every operation applied immediately
+    pub fn build(&self) -> Result<PolyGraph, PolyGraphError> {
+        let mut polyhedron = self.structure()?;
+        polyhedron.springs();
+        Ok(polyhedron)
+    }
+
+    /// Final polyhedron without its layout, for when only the structure matters
+    pub fn structure(&self) -> Result<PolyGraph, PolyGraphError> {
+        let mut polyhedron = self.seed();
+        for conway in self.operations.iter().rev() {
+            polyhedron.operate(conway)?;
+        }
+        Ok(polyhedron)
+    }
+}
+
+impl FromStr for PolyGraph {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse::<Notation>()?.build()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bones::{Notation, NotationError, PolyGraph, Transaction},
+        render::message::{ConwayMessage, PresetMessage},
+    };
+    use test_case::test_case;
+
+    #[test_case("T", 4, 6, 4; "T")]
+    #[test_case("dC", 6, 12, 8; "dC")]
+    #[test_case("dD", 12, 30, 20; "dD")]
+    #[test_case("kO", 14, 36, 24; "kO")]
+    #[test_case("tkD", 180, 270, 92; "tkD")]
+    #[test_case("bC", 48, 72, 26; "bC")]
+    #[test_case("sT", 12, 30, 20; "sT")]
+    #[test_case("jA5", 22, 40, 20; "jA5")]
+    #[test_case("aP6", 18, 36, 20; "aP6")]
+    #[test_case("eY4", 16, 32, 18; "eY4")]
+    fn build(notation: &str, vertices: usize, edges: usize, faces: usize) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        assert_eq!(graph.vertices.len(), vertices);
+        assert_eq!(graph.edges.len(), edges);
+        assert_eq!(graph.cycles.len(), faces);
+    }
+
+    #[test_case("tkD", "tkD"; "tkD")]
+    #[test_case("ddC", "C"; "ddC")]
+    #[test_case("bC", "bC"; "bC")]
+    #[test_case("aP4", "aC"; "aP4")]
+    #[test_case("kP5", "kP5"; "kP5")]
+    #[test_case("cD", "cD"; "cD")]
+    #[test_case("GC(2,1)I", "GC(2,1)I"; "GC(2,1)I")]
+    #[test_case("tGC(1, 1)uT", "tGC(1,1)uT"; "tGC(1,1)uT")]
+    #[test_case("L0pD", "L0pD"; "L0pD")]
+    #[test_case("rrgC", "gC"; "rrgC")]
+    fn name(notation: &str, name: &str) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        assert_eq!(graph.name, name);
+    }
+
+    #[test]
+    fn transactions() {
+        let notation: Notation = "tkA7".parse().unwrap();
+        assert!(matches!(notation.seed, PresetMessage::AntiPrism(7)));
+        assert!(matches!(
+            notation.transactions().as_slice(),
+            [
+                Transaction::Conway(ConwayMessage::Kis),
+                Transaction::Conway(ConwayMessage::Truncate)
+            ]
+        ));
+        assert_eq!(notation.animated().name, "A7");
+    }
+
+    #[test_case("", NotationError::Empty; "empty")]
+    #[test_case("tk", NotationError::MissingSeed; "missing seed")]
+    #[test_case("txD", NotationError::UnknownSymbol { symbol: 'x', position: 1 }; "unknown operation")]
+    #[test_case("kDa", NotationError::UnknownSymbol { symbol: 'a', position: 2 }; "after seed")]
+    #[test_case("P", NotationError::SeedArgument { seed: 'P', argument: "".into() }; "no sides")]
+    #[test_case("aY2", NotationError::SeedArgument { seed: 'Y', argument: "2".into() }; "too few sides")]
+    #[test_case("A2", NotationError::SeedArgument { seed: 'A', argument: "2".into() }; "digonal antiprism")]
+    #[test_case("A5k", NotationError::SeedArgument { seed: 'A', argument: "5k".into() }; "bad sides")]
+    #[test_case("L1C", NotationError::UnknownSymbol { symbol: 'L', position: 0 }; "lace")]
+    #[test_case("GC(2)D", NotationError::OperatorArgument { operator: "GC".into(), argument: "2".into() }; "one argument")]
+    #[test_case("GC(0,0)D", NotationError::OperatorArgument { operator: "GC".into(), argument: "0,0".into() }; "zero")]
+    #[test_case("GC(2,1", NotationError::OperatorArgument { operator: "GC".into(), argument: "2,1".into() }; "unclosed")]
+    fn errors(notation: &str, error: NotationError) {
+        assert_eq!(notation.parse::<Notation>().unwrap_err(), error);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::bones::PolyGraph;
+use crate::{
+    bones::{Face, PolyGraph, VertexId},
+    render::message::PresetMessage,
+};
 
 /*
     T = Y3
@@ -10,85 +13,94 @@ use crate::bones::PolyGraph;
 
 // Platonic Solids
 impl PolyGraph {
+    pub fn preset(preset: &PresetMessage) -> PolyGraph {
+        use PresetMessage::*;
+        match preset {
+            Prism(n) => {
+                let mut p = PolyGraph::prism(*n);
+                if n == &4 {
+                    p.name = "C".into();
+                }
+                p
+            }
+            AntiPrism(n) => PolyGraph::anti_prism(*n),
+            Pyramid(n) => {
+                let mut p = PolyGraph::pyramid(*n);
+                if n == &3 {
+                    p.name = "T".into();
+                }
+                p
+            }
+            Octahedron => PolyGraph::octahedron(),
+            Dodecahedron => PolyGraph::dodecahedron(),
+            Icosahedron => PolyGraph::icosahedron(),
+        }
+    }
+
     pub fn prism(n: usize) -> PolyGraph {
-        let mut p = PolyGraph::new_disconnected(n * 2);
-        p.name = format!("P{n}");
+        let mut faces = vec![(0..n).collect(), (n..2 * n).collect()];
         for i in 0..n {
-            // Lower polygon
-            p.connect((i % n, (i + 1) % n));
-            // Upper polygon
-            p.connect(((i % n) + n, ((i + 1) % n) + n));
-            // Connect
-            p.connect(((i % n), (i % n) + n));
-            p.connect(((i + 1) % n, ((i + 1) % n) + n));
+            // Joining the lower polygon to the upper one
+            let j = (i + 1) % n;
+            faces.push(vec![i, j, j + n, i + n]);
         }
-        p.pst();
-        p.springs();
-        p.find_cycles();
-        p.lattice();
-        p
+        PolyGraph::closed(format!("P{n}"), faces)
     }
 
     pub fn anti_prism(n: usize) -> PolyGraph {
-        let mut p = PolyGraph::new_disconnected(n * 2);
-        p.name = format!("A{n}");
+        let mut faces = vec![(0..n).collect(), (n..2 * n).collect()];
         for i in 0..n {
-            // Lower polygon
-            p.connect((i % n, (i + 1) % n));
-            // Upper polygon
-            p.connect(((i % n) + n, ((i + 1) % n) + n));
-            // Connect
-            p.connect(((i % n), (i % n) + n));
-            p.connect(((i + 1) % n, ((i + 1) % n) + n));
-
-            p.connect(((i % n), ((i + 1) % n) + n));
+            // Two triangles between each edge of the lower polygon and the upper one
+            let j = (i + 1) % n;
+            faces.push(vec![i, j, j + n]);
+            faces.push(vec![i, j + n, i + n]);
         }
-        p.pst();
-        p.springs();
-        p.find_cycles();
-        p.lattice();
-        p
+        PolyGraph::closed(format!("A{n}"), faces)
     }
 
     pub fn pyramid(n: usize) -> PolyGraph {
-        let mut p = PolyGraph::new_disconnected(n + 1);
-        p.name = format!("Y{n}");
+        let mut faces = vec![(0..n).collect()];
         for i in 0..n {
-            p.connect((i, (i + 1) % n));
-            p.connect((i, n));
+            faces.push(vec![i, (i + 1) % n, n]);
         }
-        p.pst();
+        PolyGraph::closed(format!("Y{n}"), faces)
+    }
+
+    /// Laid out in a lattice, from faces given by the ids of their vertices
+    fn closed(name: String, faces: Vec<Vec<VertexId>>) -> PolyGraph {
+        let mut p = PolyGraph::from_cycles(faces.into_iter().map(Face::new).collect())
+            .expect("the faces make up a closed polyhedron");
+        p.name = name;
         p.springs();
-        p.find_cycles();
         p.lattice();
+        p.orient_outward();
         p
     }
 
     pub fn octahedron() -> PolyGraph {
         let mut p = PolyGraph::pyramid(3);
-        let edges = p.ambo();
-        p.contract_edges(edges);
-        p.pst();
+        let edges = p.ambo().expect("a triangular pyramid is closed");
+        p.contract_edges(edges)
+            .expect("contracting the edges of a truncated pyramid leaves it closed");
         p.springs();
         p.lattice();
+        p.orient_outward();
         p.name = "O".into();
         p
     }
     pub fn dodecahedron() -> PolyGraph {
-        let mut p = PolyGraph::anti_prism(5);
-        let edges = p.expand(false);
-        p.contract_edges(edges);
-        p.truncate(Some(5));
-        p.pst();
+        let mut p = PolyGraph::pyramid(3);
+        p.gyro().expect("a triangular pyramid is closed");
         p.springs();
+        p.orient_outward();
         p.name = "D".into();
         p
     }
     pub fn icosahedron() -> PolyGraph {
         let mut p = PolyGraph::anti_prism(5);
-        p.kis(Some(5));
-        p.pst();
+        p.kis(Some(5)).expect("an antiprism is closed");
         p.springs();
+        p.orient_outward();
         p.name = "I".into();
         p
     }
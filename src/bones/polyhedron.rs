@@ -1,22 +1,34 @@
 use crate::{
-    bones::{Edge, PolyGraph, Transaction},
-    render::message::ConwayMessage,
+    bones::{Edge, Origin, PolyGraph, PolyGraphError, Transaction, CANONICAL_TOLERANCE},
+    render::message::{ConwayMessage, LayoutMessage},
 };
 use std::time::{Duration, Instant};
 use ultraviolet::{Lerp, Vec3};
 
 const SPEED_DAMPENING: f32 = 0.92;
+/// Iterations of the canonical form solver per frame
+const CANONICAL_STEPS: usize = 4;
+/// How far inside out a settled layout has to be before its faces are turned around,
+/// as a fraction of the cube of its radius, so that flat ones don't flip back and forth
+const INSIDE_OUT_VOLUME: f32 = 0.25;
 
 // Operations
 impl PolyGraph {
     fn apply_spring_forces(&mut self, speed: f32, second: f32) {
-        let diameter = *self.dist.values().max().unwrap_or(&1) as f32;
+        // The longest springs reach across the polyhedron
+        let diameter = self
+            .springs
+            .values()
+            .copied()
+            .max()
+            .unwrap_or_default()
+            .max(1) as f32;
         let diameter_spring_length = self.edge_length * 2.0;
-        let (edges, contracting): (std::collections::hash_set::Iter<Edge>, bool) =
+        let (edges, contracting): (Box<dyn Iterator<Item = &Edge>>, bool) =
             if let Some(Transaction::Contraction(edges)) = self.transactions.first() {
-                (edges.iter(), true)
+                (Box::new(edges.iter()), true)
             } else {
-                (self.springs.iter(), false)
+                (Box::new(self.springs.keys()), false)
             };
 
         for e in edges {
@@ -31,7 +43,8 @@ impl PolyGraph {
                 *self.positions.entry(v).or_default() = v_position.lerp(u_position, f);
                 *self.positions.entry(u).or_default() = u_position.lerp(v_position, f);
             } else {
-                let target_length = diameter_spring_length * (self.dist[e] as f32 / diameter);
+                let target_length = diameter_spring_length
+                    * (self.springs.get(e).copied().unwrap_or_default() as f32 / diameter);
                 let f = diff * (target_length - spring_length) / speed * second;
                 *self.speeds.entry(v).or_default() = (self.speeds[&v] + f) * SPEED_DAMPENING;
                 *self.speeds.entry(u).or_default() = (self.speeds[&u] - f) * SPEED_DAMPENING;
@@ -56,11 +69,71 @@ impl PolyGraph {
         self.edge_length -= distance / speed * second;
     }
 
-    pub fn update(&mut self, speed: f32, second: f32) {
-        self.center();
-        self.resize(speed, second);
-        self.apply_spring_forces(speed, second);
-        self.process_transactions(speed);
+    pub fn update(
+        &mut self,
+        layout: &LayoutMessage,
+        speed: f32,
+        second: f32,
+    ) -> Result<(), PolyGraphError> {
+        match layout {
+            // Springs still drive any animations in progress
+            LayoutMessage::Canonical if self.transactions.is_empty() => {
+                self.canonicalize(CANONICAL_TOLERANCE, CANONICAL_STEPS);
+            }
+            // Contractions are still pulled together by their springs
+            LayoutMessage::BarnesHut
+                if !matches!(self.transactions.first(), Some(Transaction::Contraction(_))) =>
+            {
+                self.center();
+                self.resize(speed, second);
+                self.apply_barnes_hut_forces(speed, second);
+            }
+            _ => {
+                self.center();
+                self.resize(speed, second);
+                self.apply_spring_forces(speed, second);
+            }
+        }
+        // Layouts can settle inside out, from a start with no sense of outside
+        if self.transactions.is_empty() {
+            let radius = self.positions.values().map(|p| p.mag()).fold(0.0, f32::max);
+            if self.volume() < -INSIDE_OUT_VOLUME * radius.powi(3) {
+                self.orient_outward();
+            }
+        }
+        self.process_transactions(speed)
+    }
+
+    /// Start out from the layout of `other`, so that switching to this polyhedron
+    /// moves smoothly. Vertices it doesn't share start on the closest one it does.
+    pub fn morph_from(&mut self, other: &PolyGraph) {
+        // Layouts drift in size, so compare them at the same scale
+        let radius = |graph: &PolyGraph| {
+            graph
+                .positions
+                .values()
+                .map(|p| p.mag())
+                .fold(0.0, f32::max)
+        };
+        let scale = radius(other) / radius(self).max(f32::EPSILON);
+        for v in self.vertices.clone() {
+            let position = self.positions.get(&v).copied().unwrap_or_default() * scale;
+            let start = other.positions.get(&v).copied().unwrap_or_else(|| {
+                other
+                    .positions
+                    .values()
+                    .copied()
+                    .min_by(|a, b| {
+                        (*a - position)
+                            .mag_sq()
+                            .total_cmp(&(*b - position).mag_sq())
+                    })
+                    .unwrap_or(position)
+            });
+            self.positions.insert(v, start);
+            self.speeds.insert(v, Vec3::zero());
+        }
+        self.edge_length = other.edge_length;
     }
 
     pub fn face_positions(&self, face_index: usize) -> Vec<Vec3> {
@@ -76,7 +149,25 @@ impl PolyGraph {
         vertices.iter().fold(Vec3::zero(), |a, &b| a + b) / vertices.len() as f32
     }
 
-    pub fn process_transactions(&mut self, speed: f32) {
+    /// Unit normal of a face, pointing out of the polyhedron.
+    /// Zero if the face has collapsed to a point or a line.
+    pub fn face_normal(&self, face_index: usize) -> Vec3 {
+        let positions = self.face_positions(face_index);
+        let n = positions.len();
+        let normal = (0..n)
+            .map(|i| positions[i].cross(positions[(i + 1) % n]))
+            .fold(Vec3::zero(), |a, b| a + b);
+        if normal.mag_sq() == 0.0 {
+            normal
+        } else {
+            normal.normalized()
+        }
+    }
+
+    /// Carry out the next transaction, if it's ready.
+    /// Changes to the structure that fail or leave the graph broken are rolled back,
+    /// along with every transaction still queued.
+    pub fn process_transactions(&mut self, speed: f32) -> Result<(), PolyGraphError> {
         if let Some(transaction) = self.transactions.first().cloned() {
             use Transaction::*;
             match transaction {
@@ -85,90 +176,41 @@ impl PolyGraph {
                         .iter()
                         .any(|e| (self.positions[&e.v()] - self.positions[&e.u()]).mag() > 0.02)
                     {
-                        // Contract them in the graph
-                        self.contract_edges(edges);
-                        self.pst();
+                        self.checked("contraction", |graph| {
+                            graph.transactions.remove(0);
+                            // Contract them in the graph
+                            graph.contract_edges(edges)?;
+                            graph.validate()
+                        })?;
                         self.springs();
-                        self.transactions.remove(0);
                     }
                 }
                 Release(edges) => {
-                    for e in edges.into_iter() {
-                        self.disconnect(e);
-                    }
-                    self.pst();
+                    self.checked("release", |graph| {
+                        graph.transactions.remove(0);
+                        graph.release(edges)?;
+                        graph.validate()
+                    })?;
                     self.springs();
-                    self.find_cycles();
-                    self.transactions.remove(0);
                 }
                 Conway(conway) => {
-                    self.transactions.remove(0);
-                    use ConwayMessage::*;
-                    use Transaction::*;
-                    let new_transactions = match conway {
-                        Dual => {
-                            let edges = self.expand(false);
-                            vec![
-                                Wait(Instant::now() + Duration::from_millis((65.0 * speed) as u64)),
-                                Contraction(edges),
-                                Name('d'),
-                            ]
-                        }
-                        Join => {
-                            let edges = self.kis(Option::None);
-                            vec![
-                                //Wait(Instant::now() + Duration::from_secs(1)),
-                                Release(edges),
-                                Name('j'),
-                            ]
-                        }
-                        Ambo => {
-                            let edges = self.ambo();
-                            vec![Contraction(edges), Name('a')]
-                        }
-                        Kis => {
-                            self.kis(Option::None);
-                            vec![Name('k')]
-                        }
-                        Truncate => {
-                            self.truncate(Option::None);
-                            vec![Name('t')]
-                        }
-                        Expand => {
-                            self.expand(false);
-                            vec![Name('e')]
-                        }
-                        Snub => {
-                            self.expand(true);
-                            vec![Name('s')]
-                        }
-                        Bevel => {
-                            vec![
-                                Conway(Truncate),
-                                Wait(Instant::now() + Duration::from_millis(500)),
-                                Conway(Ambo),
-                                Name('b'),
-                            ]
-                        }
-                    };
-                    self.cycles.sort_by_key(|c| usize::MAX - c.len());
+                    let mut new_transactions = Vec::new();
+                    self.checked(&conway.to_string(), |graph| {
+                        graph.transactions.remove(0);
+                        graph.begin_generation();
+                        new_transactions = graph.animate(&conway, speed)?;
+                        graph.validate()
+                    })?;
+                    // Bevel is renamed by the operations it queued
+                    if conway != ConwayMessage::Bevel {
+                        new_transactions.push(Name(conway.notation()));
+                    }
+                    self.sort_faces();
                     self.transactions = [new_transactions, self.transactions.clone()].concat();
-                    self.pst();
                     self.springs();
                 }
                 Name(c) => {
-                    if c == 'b' {
-                        self.name = self.name[2..].to_string();
-                    }
-                    if c == 'd' && &self.name[0..1] == "d" {
-                        self.name = self.name[1..].to_string();
-                    } else {
-                        self.name = format!("{c}{}", self.name);
-                    }
-                    self.transactions.remove(0);
-                }
-                ShortenName(n) => {
-                    self.name = self.name[n..].to_string();
+                    self.rename(&c);
                     self.transactions.remove(0);
                 }
                 Wait(instant) => {
@@ -176,8 +218,138 @@ impl PolyGraph {
                         self.transactions.remove(0);
                     }
                 }
+                Recorded(_) => {
+                    self.transactions.remove(0);
+                }
                 None => {}
             }
         }
+        Ok(())
+    }
+
+    /// Make a change to the structure, restoring the graph as it was
+    /// and dropping any queued transactions if it goes wrong.
+    /// The records of the operations that were dropped are kept in `rolled_back`.
+    /// Faces are wound outward again afterwards.
+    fn checked(
+        &mut self,
+        what: &str,
+        change: impl FnOnce(&mut Self) -> Result<(), PolyGraphError>,
+    ) -> Result<(), PolyGraphError> {
+        let snapshot = self.clone();
+        change(self).inspect_err(|error| {
+            log::error!("rolled back {what} on {}: {error}", snapshot.name);
+            *self = snapshot;
+            self.rolled_back = std::mem::take(&mut self.transactions)
+                .into_iter()
+                .filter_map(|transaction| match transaction {
+                    Transaction::Recorded(record) => Some(record),
+                    _ => None,
+                })
+                .collect();
+        })?;
+        self.orient_outward();
+        Ok(())
+    }
+
+    /// Make a change which adds vertices, starting each new one on the part of the
+    /// polyhedron it came from so that the new faces grow out of the old ones
+    fn grow(
+        &mut self,
+        change: impl FnOnce(&mut Self) -> Result<(), PolyGraphError>,
+    ) -> Result<(), PolyGraphError> {
+        let positions = self.positions.clone();
+        let centroids = (0..self.cycles.len())
+            .map(|i| self.face_centroid(i))
+            .collect::<Vec<_>>();
+        change(self)?;
+        for (v, position) in self.positions.iter_mut() {
+            if positions.contains_key(v) {
+                continue;
+            }
+            let start = match self.provenance.vertices.get(v) {
+                Some(Origin::Vertex(u)) => positions.get(u).copied(),
+                Some(Origin::Edge(e)) => positions
+                    .get(&e.v())
+                    .zip(positions.get(&e.u()))
+                    .map(|(&a, &b)| a.lerp(b, 0.5)),
+                Some(Origin::Face(i)) => centroids.get(*i).copied(),
+                None => None,
+            };
+            *position = start.unwrap_or(*position);
+        }
+        Ok(())
+    }
+
+    /// Change the structure for an operation, returning the transactions that animate it
+    fn animate(
+        &mut self,
+        conway: &ConwayMessage,
+        speed: f32,
+    ) -> Result<Vec<Transaction>, PolyGraphError> {
+        use ConwayMessage::*;
+        use Transaction::*;
+        let spread = || Wait(Instant::now() + Duration::from_millis((65.0 * speed) as u64));
+        Ok(match conway {
+            Dual => {
+                let edges = self.expand(false)?;
+                vec![spread(), Contraction(edges)]
+            }
+            Join => {
+                let edges = self.kis(Option::None)?;
+                vec![
+                    //Wait(Instant::now() + Duration::from_secs(1)),
+                    Release(edges),
+                ]
+            }
+            Ambo => {
+                let edges = self.ambo()?;
+                vec![Contraction(edges)]
+            }
+            Kis => {
+                self.kis(Option::None)?;
+                vec![]
+            }
+            Truncate => {
+                self.truncate(Option::None)?;
+                vec![]
+            }
+            Expand => {
+                self.expand(false)?;
+                vec![]
+            }
+            Snub => {
+                self.expand(true)?;
+                vec![]
+            }
+            Bevel => {
+                vec![
+                    Conway(Ambo),
+                    Wait(Instant::now() + Duration::from_millis(500)),
+                    Conway(Truncate),
+                ]
+            }
+            Needle => self.needle().map(|_| vec![])?,
+            Zip => self.zip().map(|_| vec![])?,
+            Ortho => self.ortho().map(|_| vec![])?,
+            Chamfer => self.chamfer().map(|_| vec![])?,
+            Subdivide => self.subdivide().map(|_| vec![])?,
+            Gyro => self.gyro().map(|_| vec![])?,
+            Meta => self.meta().map(|_| vec![])?,
+            // Flattened onto the mirror, then left to spring back out the other side
+            Reflect => {
+                self.reflect();
+                for position in self.positions.values_mut() {
+                    position.x *= 0.05;
+                }
+                vec![spread()]
+            }
+            Loft => self.grow(Self::loft).map(|_| vec![spread()])?,
+            Propeller => self.grow(Self::propeller).map(|_| vec![spread()])?,
+            Whirl => self.grow(Self::whirl).map(|_| vec![spread()])?,
+            Quinto => self.grow(Self::quinto).map(|_| vec![spread()])?,
+            JoinLace => self.grow(Self::join_lace).map(|_| vec![spread()])?,
+            GoldbergCoxeter(m, n) => self.goldberg_coxeter(*m, *n).map(|_| vec![])?,
+        })
     }
 }
@@ -0,0 +1,328 @@
+use crate::bones::{Edge, Face, PolyGraph, VertexId};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+
+/// Part of the polyhedron an operation started from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Origin {
+    Vertex(VertexId),
+    Edge(Edge),
+    /// By index into the `cycles` the operation started from
+    Face(usize),
+}
+
+/// Where every vertex, edge and face came from in the previous generation,
+/// which is the polyhedron as it was before the latest operation
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    pub vertices: HashMap<VertexId, Origin>,
+    pub edges: HashMap<Edge, Origin>,
+    pub faces: HashMap<Face, Origin>,
+    /// Lineage of each face of the previous generation
+    lineages: Vec<usize>,
+}
+
+impl Provenance {
+    /// Faces share a lineage when they came from faces which did,
+    /// or were born in the same generation out of the same kind of part
+    pub fn lineage(&self, face: &Face) -> Option<usize> {
+        let born = self.lineages.iter().max().map_or(0, |l| l + 1);
+        match self.faces.get(face)? {
+            Origin::Face(i) => self.lineages.get(*i).copied(),
+            Origin::Vertex(_) => Some(born),
+            Origin::Edge(_) => Some(born + 1),
+        }
+    }
+}
+
+/// The structure before a change, for tracing where the parts after it came from
+pub struct Snapshot {
+    pub cycles: Vec<Face>,
+    pub edges: HashSet<Edge>,
+    /// Faces around each vertex, by index into `cycles`
+    faces: HashMap<VertexId, Vec<usize>>,
+    provenance: Provenance,
+}
+
+impl Snapshot {
+    /// Where a part of the snapshot came from in the previous generation
+    pub fn trace(&self, origin: Origin) -> Option<Origin> {
+        match origin {
+            Origin::Vertex(v) => self.provenance.vertices.get(&v),
+            Origin::Edge(e) => self.provenance.edges.get(&e),
+            Origin::Face(i) => self.provenance.faces.get(self.cycles.get(i)?),
+        }
+        .copied()
+    }
+
+    /// First face of the snapshot with every one of `vertices` on it
+    pub fn face_around(&self, vertices: &[VertexId]) -> Option<usize> {
+        let (first, rest) = vertices.split_first()?;
+        self.faces
+            .get(first)?
+            .iter()
+            .copied()
+            .find(|&i| rest.iter().all(|v| self.cycles[i].containz(v)))
+    }
+
+    /// The edge of the snapshot running between two vertices, or between two faces
+    fn between(&self, a: Origin, b: Origin) -> Option<Origin> {
+        match (a, b) {
+            (Origin::Vertex(x), Origin::Vertex(y)) => {
+                let e = (x, y).into();
+                self.edges.contains(&e).then_some(Origin::Edge(e))
+            }
+            (Origin::Face(i), Origin::Face(j)) if i != j => {
+                let shared = self.cycles.get(i)?.edges();
+                self.cycles
+                    .get(j)?
+                    .edges()
+                    .into_iter()
+                    .find(|e| shared.contains(e))
+                    .map(Origin::Edge)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl PolyGraph {
+    /// Start a new generation, in which every part of the polyhedron comes from itself
+    pub fn begin_generation(&mut self) {
+        let lineages = self.face_lineages();
+        self.provenance = Provenance {
+            vertices: self
+                .vertices
+                .iter()
+                .map(|&v| (v, Origin::Vertex(v)))
+                .collect(),
+            edges: self.edges.iter().map(|&e| (e, Origin::Edge(e))).collect(),
+            faces: self
+                .cycles
+                .iter()
+                .enumerate()
+                .map(|(i, cycle)| (cycle.clone(), Origin::Face(i)))
+                .collect(),
+            lineages,
+        };
+    }
+
+    /// Lineage of every face. Faces nothing is known about are told apart by their sides.
+    pub fn face_lineages(&self) -> Vec<usize> {
+        let mut sizes = self.cycles.iter().map(Face::len).collect::<Vec<_>>();
+        sizes.sort();
+        sizes.dedup();
+        self.cycles
+            .iter()
+            .map(|cycle| {
+                self.provenance
+                    .lineage(cycle)
+                    .unwrap_or_else(|| sizes.binary_search(&cycle.len()).unwrap_or_default())
+            })
+            .collect()
+    }
+
+    /// Hold on to the structure before changing it
+    pub fn snapshot(&mut self) -> Snapshot {
+        let mut faces: HashMap<VertexId, Vec<usize>> = HashMap::default();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            for &v in cycle.iter() {
+                faces.entry(v).or_default().push(i);
+            }
+        }
+        Snapshot {
+            cycles: self.cycles.clone(),
+            edges: self.edges.clone(),
+            faces,
+            provenance: std::mem::take(&mut self.provenance),
+        }
+    }
+
+    /// Carry the provenance on through a change to the structure, given where the parts
+    /// came from in `before`. `faces` goes with `cycles`. Vertices which aren't in
+    /// `vertices` were kept, and edges which aren't in `edges` run between the parts
+    /// that their ends or the faces on either side of them came from.
+    pub fn inherit(
+        &mut self,
+        before: Snapshot,
+        vertices: &HashMap<VertexId, Origin>,
+        edges: &HashMap<Edge, Origin>,
+        faces: &[Origin],
+    ) {
+        let vertex = |v: VertexId| vertices.get(&v).copied().unwrap_or(Origin::Vertex(v));
+        let mut sides: HashMap<Edge, Vec<Origin>> = HashMap::default();
+        for (cycle, &origin) in self.cycles.iter().zip(faces) {
+            for e in cycle.edges() {
+                sides.entry(e).or_default().push(origin);
+            }
+        }
+        let edge = |e: &Edge| {
+            if let Some(&origin) = edges.get(e) {
+                return origin;
+            }
+            let (a, b) = (vertex(e.v()), vertex(e.u()));
+            let sides = sides.get(e).map(Vec::as_slice).unwrap_or_default();
+            if a == b {
+                a
+            } else if let Some(origin) = before.between(a, b) {
+                origin
+            } else if let [first, rest @ ..] = sides {
+                if rest.iter().all(|s| s == first) {
+                    *first
+                } else {
+                    rest.iter()
+                        .find_map(|&s| before.between(*first, s))
+                        .unwrap_or(*first)
+                }
+            } else {
+                a
+            }
+        };
+
+        self.provenance = Provenance {
+            vertices: self
+                .vertices
+                .iter()
+                .filter_map(|&v| Some((v, before.trace(vertex(v))?)))
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .filter_map(|e| Some((*e, before.trace(edge(e))?)))
+                .collect(),
+            faces: self
+                .cycles
+                .iter()
+                .zip(faces)
+                .filter_map(|(cycle, &origin)| Some((cycle.clone(), before.trace(origin)?)))
+                .collect(),
+            lineages: before.provenance.lineages,
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bones::{Origin, PolyGraph, Transaction},
+        render::message::ConwayMessage,
+    };
+    use test_case::test_case;
+    use ultraviolet::Vec3;
+
+    /// Truncated cubes keep a lineage for the squares' octagons and start one for the triangles
+    #[test]
+    fn truncate() {
+        let mut graph: PolyGraph = "C".parse().unwrap();
+        graph.operate(&ConwayMessage::Truncate).unwrap();
+        for (cycle, lineage) in graph.cycles.iter().zip(graph.face_lineages()) {
+            let origin = graph.provenance.faces[cycle];
+            match cycle.len() {
+                8 => assert!(matches!(origin, Origin::Face(_)) && lineage == 0),
+                3 => assert!(matches!(origin, Origin::Vertex(_)) && lineage == 1),
+                _ => unreachable!(),
+            }
+        }
+        // The edges of the triangles came from the vertices they cut off
+        let vertices = graph
+            .edges
+            .iter()
+            .filter(|e| matches!(graph.provenance.edges[e], Origin::Vertex(_)))
+            .count();
+        assert_eq!(vertices, 24);
+    }
+
+    /// Faces are found from the vertices on them
+    #[test]
+    fn face_around() {
+        let mut graph = PolyGraph::prism(4);
+        let before = graph.snapshot();
+        let i = before.face_around(&[0, 1, 5]).unwrap();
+        assert!([0, 1, 4, 5].iter().all(|v| before.cycles[i].containz(v)));
+        assert_eq!(before.face_around(&[0, 6]), None);
+        assert_eq!(before.face_around(&[]), None);
+    }
+
+    /// Every part of the result of every operation knows where it came from
+    #[test_case("C"; "C")]
+    #[test_case("aD"; "aD")]
+    #[test_case("P5"; "P5")]
+    fn complete(notation: &str) {
+        use strum::IntoEnumIterator;
+        for conway in ConwayMessage::iter() {
+            let mut graph: PolyGraph = notation.parse().unwrap();
+            if graph.operate(&conway).is_err() {
+                continue;
+            }
+            assert_eq!(
+                graph.provenance.vertices.len(),
+                graph.vertices.len(),
+                "{conway}"
+            );
+            assert_eq!(graph.provenance.edges.len(), graph.edges.len(), "{conway}");
+            assert!(
+                graph
+                    .cycles
+                    .iter()
+                    .all(|c| graph.provenance.faces.contains_key(c)),
+                "{conway}"
+            );
+        }
+    }
+
+    /// Kis keeps the lineages of the faces it splits, and the dual swaps vertices and faces
+    #[test]
+    fn kis_dual() {
+        let mut graph: PolyGraph = "C".parse().unwrap();
+        graph.operate(&ConwayMessage::Kis).unwrap();
+        assert!(graph
+            .cycles
+            .iter()
+            .all(|c| matches!(graph.provenance.faces[c], Origin::Face(_))));
+        assert_eq!(graph.face_lineages(), vec![0; 24]);
+
+        graph.operate(&ConwayMessage::Dual).unwrap();
+        assert!(graph
+            .cycles
+            .iter()
+            .all(|c| matches!(graph.provenance.faces[c], Origin::Vertex(_))));
+        let faces = graph
+            .vertices
+            .iter()
+            .filter(|v| matches!(graph.provenance.vertices[v], Origin::Face(_)))
+            .count();
+        assert_eq!(faces, 24);
+    }
+
+    /// Animated operations trace their contractions and releases back to the start
+    #[test_case(ConwayMessage::Ambo, &[(3, Origin::Face(0)), (4, Origin::Vertex(0))]; "ambo")]
+    #[test_case(ConwayMessage::Dual, &[(4, Origin::Vertex(0))]; "dual")]
+    #[test_case(ConwayMessage::Join, &[(4, Origin::Edge((0, 1).into()))]; "join")]
+    fn animated(conway: ConwayMessage, kinds: &[(usize, Origin)]) {
+        let mut graph = PolyGraph::octahedron();
+        graph.transactions = vec![Transaction::Conway(conway)];
+        while let Some(transaction) = graph.transactions.first() {
+            match transaction {
+                Transaction::Wait(_) => {
+                    graph.transactions.remove(0);
+                    continue;
+                }
+                Transaction::Contraction(edges) => {
+                    for e in edges.clone() {
+                        graph.positions.insert(e.v(), Vec3::zero());
+                        graph.positions.insert(e.u(), Vec3::zero());
+                    }
+                }
+                _ => {}
+            }
+            graph.process_transactions(10.0).unwrap();
+        }
+        for cycle in graph.cycles.iter() {
+            let (_, kind) = kinds.iter().find(|(n, _)| *n == cycle.len()).unwrap();
+            let origin = graph.provenance.faces[cycle];
+            assert_eq!(
+                std::mem::discriminant(&origin),
+                std::mem::discriminant(kind)
+            );
+        }
+    }
+}
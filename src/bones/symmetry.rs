@@ -0,0 +1,458 @@
+use crate::bones::{Edge, PolyGraph, VertexId};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use std::fmt::Display;
+
+/// Point groups in Schoenflies notation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointGroup {
+    /// n-fold rotation
+    C(usize),
+    /// n-fold rotation with n vertical mirrors
+    Cv(usize),
+    /// n-fold rotation with a horizontal mirror, `Cs` when n is one
+    Ch(usize),
+    /// n-fold rotoreflection, `Ci` when n is two
+    S(usize),
+    /// n-fold rotation with n perpendicular 2-fold rotations
+    D(usize),
+    /// `D(n)` with n diagonal mirrors
+    Dd(usize),
+    /// `D(n)` with a horizontal mirror and n vertical mirrors
+    Dh(usize),
+    /// Chiral tetrahedral
+    T,
+    /// Full tetrahedral
+    Td,
+    /// Pyritohedral
+    Th,
+    /// Chiral octahedral
+    O,
+    /// Full octahedral
+    Oh,
+    /// Chiral icosahedral
+    I,
+    /// Full icosahedral
+    Ih,
+}
+
+impl Display for PointGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use PointGroup::*;
+        match self {
+            C(n) => f.write_fmt(format_args!("C{n}")),
+            Cv(n) => f.write_fmt(format_args!("C{n}v")),
+            Ch(1) => f.write_str("Cs"),
+            Ch(n) => f.write_fmt(format_args!("C{n}h")),
+            S(2) => f.write_str("Ci"),
+            S(n) => f.write_fmt(format_args!("S{n}")),
+            D(n) => f.write_fmt(format_args!("D{n}")),
+            Dd(n) => f.write_fmt(format_args!("D{n}d")),
+            Dh(n) => f.write_fmt(format_args!("D{n}h")),
+            _ => f.write_fmt(format_args!("{self:?}")),
+        }
+    }
+}
+
+/// The isometry of the canonical polyhedron which realizes an automorphism
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Identity,
+    /// Rotation by a fraction of a turn
+    Rotation(usize),
+    /// Mirror through a plane
+    Reflection,
+    /// Mirror through the center
+    Inversion,
+    /// Rotation by a fraction of a turn followed by a reflection across a perpendicular plane
+    Rotoreflection(usize),
+}
+
+/// A permutation of the vertices which preserves the edges and faces
+#[derive(Debug, Clone)]
+pub struct Automorphism {
+    pub vertices: HashMap<VertexId, VertexId>,
+    /// Image of each face, by index into `cycles`
+    pub faces: Vec<usize>,
+    /// Whether it turns the polyhedron inside out, like a mirror
+    pub mirrored: bool,
+}
+
+impl Automorphism {
+    pub fn edge(&self, e: Edge) -> Edge {
+        (self.vertices[&e.v()], self.vertices[&e.u()]).into()
+    }
+
+    /// Number of times it has to be applied to get back to where it started
+    pub fn order(&self) -> usize {
+        let mut order = 1;
+        let mut seen = HashSet::default();
+        for &v in self.vertices.keys() {
+            let mut length = 0;
+            let mut u = v;
+            while seen.insert(u) {
+                u = self.vertices[&u];
+                length += 1;
+            }
+            if length > 0 {
+                order = lcm(order, length);
+            }
+        }
+        order
+    }
+
+    /// Whether any vertex, edge or face is mapped onto itself
+    fn fixes_anything(&self, graph: &PolyGraph) -> bool {
+        self.vertices.iter().any(|(v, u)| v == u)
+            || graph.edges.iter().any(|&e| self.edge(e) == e)
+            || self.faces.iter().enumerate().any(|(i, &j)| i == j)
+    }
+
+    pub fn operation(&self, graph: &PolyGraph) -> Operation {
+        match (self.mirrored, self.order()) {
+            (false, 1) => Operation::Identity,
+            (false, n) => Operation::Rotation(n),
+            // Mirror planes cut through the surface, inversions leave it untouched
+            (true, 2) if self.fixes_anything(graph) => Operation::Reflection,
+            (true, 2) => Operation::Inversion,
+            (true, n) => Operation::Rotoreflection(n),
+        }
+    }
+}
+
+/// The symmetries of a polyhedron, and the parts of it which they can swap
+#[derive(Debug, Clone)]
+pub struct Symmetry {
+    pub group: PointGroup,
+    pub vertex_orbits: Vec<Vec<VertexId>>,
+    pub edge_orbits: Vec<Vec<Edge>>,
+    /// Faces by index into `cycles`
+    pub face_orbits: Vec<Vec<usize>>,
+}
+
+impl Symmetry {
+    /// Number of the orbit each face of `graph` is in. Orbits are numbered
+    /// by the size of their faces, then how many faces they have, then their first face.
+    pub fn face_orbit_numbers(&self, graph: &PolyGraph) -> Vec<usize> {
+        let mut orbits = self.face_orbits.iter().collect::<Vec<_>>();
+        orbits.sort_by_key(|orbit| (graph.cycles[orbit[0]].len(), orbit.len(), orbit[0]));
+        let mut numbers = vec![0; graph.cycles.len()];
+        for (number, orbit) in orbits.into_iter().enumerate() {
+            for &face in orbit {
+                numbers[face] = number;
+            }
+        }
+        numbers
+    }
+}
+
+impl PolyGraph {
+    /// Every automorphism of the graph which preserves its faces.
+    /// Polyhedra are 3-connected and planar, so each is determined
+    /// by where it sends a single directed edge, and whether it is mirrored.
+    pub fn automorphisms(&self) -> Vec<Automorphism> {
+        let graph = self;
+        let Some(&start) = graph.edges.iter().next() else {
+            return vec![];
+        };
+
+        // Face on the left of each directed edge
+        let left: HashMap<(VertexId, VertexId), usize> = graph
+            .across()
+            .into_iter()
+            .map(|((b, a), i)| ((a, b), i))
+            .collect();
+        // The next directed edge around its first vertex, in either direction
+        let clockwise = graph.rotations();
+        let counter: HashMap<_, _> = clockwise.iter().map(|(&(a, z), &b)| ((a, b), z)).collect();
+
+        let degree = |v: VertexId| graph.connections(v).len();
+        let signature = |(a, b): (VertexId, VertexId)| {
+            (
+                degree(a),
+                degree(b),
+                graph.cycles[left[&(a, b)]].len(),
+                graph.cycles[left[&(b, a)]].len(),
+            )
+        };
+        let start = (start.v(), start.u());
+
+        let mut automorphisms = vec![];
+        for image in left.keys() {
+            for mirrored in [false, true] {
+                // Mirroring swaps the faces on either side
+                let (a, b, l, r) = signature(*image);
+                let found = if mirrored { (a, b, r, l) } else { (a, b, l, r) };
+                if signature(start) != found {
+                    continue;
+                }
+
+                let next = if mirrored { &counter } else { &clockwise };
+                let mut darts = HashMap::default();
+                let mut queue = vec![(start, *image)];
+                let mut consistent = true;
+                while let Some((d, e)) = queue.pop() {
+                    match darts.get(&d) {
+                        Some(&existing) if existing != e => {
+                            consistent = false;
+                            break;
+                        }
+                        Some(_) => continue,
+                        None => {
+                            darts.insert(d, e);
+                        }
+                    }
+                    queue.push(((d.1, d.0), (e.1, e.0)));
+                    queue.push(((d.0, clockwise[&d]), (e.0, next[&e])));
+                }
+                if !consistent || darts.len() != left.len() {
+                    continue;
+                }
+
+                let mut vertices = HashMap::default();
+                for (d, e) in darts.iter() {
+                    if *vertices.entry(d.0).or_insert(e.0) != e.0 {
+                        consistent = false;
+                    }
+                }
+                let images: HashSet<_> = vertices.values().collect();
+                if !consistent || images.len() != vertices.len() {
+                    continue;
+                }
+
+                let mut faces = vec![0; graph.cycles.len()];
+                for (d, e) in darts {
+                    let e = if mirrored { (e.1, e.0) } else { e };
+                    faces[left[&d]] = left[&e];
+                }
+                automorphisms.push(Automorphism {
+                    vertices,
+                    faces,
+                    mirrored,
+                });
+            }
+        }
+        automorphisms
+    }
+
+    /// Symmetry of the structure alone, found from its automorphisms rather than the positions,
+    /// which are rarely exactly symmetric while the springs are settling. Every automorphism
+    /// of a polyhedron is an isometry of its canonical form (Mani's theorem), so this is the
+    /// point group that form has. Kept until the structure next changes.
+    pub fn combinatorial_symmetry(&self) -> &Symmetry {
+        self.symmetry.get_or_init(|| self.find_symmetry())
+    }
+
+    fn find_symmetry(&self) -> Symmetry {
+        let automorphisms = self.automorphisms();
+        let operations: Vec<Operation> = automorphisms
+            .iter()
+            .map(|automorphism| automorphism.operation(self))
+            .collect();
+
+        let rotations = automorphisms.iter().filter(|a| !a.mirrored).count();
+        let reflections = operations
+            .iter()
+            .filter(|&&o| o == Operation::Reflection)
+            .count();
+        let largest = operations
+            .iter()
+            .filter_map(|o| match o {
+                Operation::Rotation(n) => Some(*n),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(1);
+        let chiral = rotations == automorphisms.len();
+
+        use PointGroup::*;
+        let group = if rotations <= 1 {
+            match reflections {
+                _ if chiral => C(1),
+                1 => Ch(1),
+                _ => S(2),
+            }
+        } else if largest == rotations {
+            let n = rotations;
+            if chiral {
+                C(n)
+            } else if reflections == n {
+                Cv(n)
+            } else if reflections == 1 {
+                Ch(n)
+            } else {
+                S(2 * n)
+            }
+        } else if largest * 2 == rotations {
+            let n = largest;
+            if chiral {
+                D(n)
+            } else if reflections == n + 1 {
+                Dh(n)
+            } else {
+                Dd(n)
+            }
+        } else {
+            match (rotations, reflections) {
+                (12, _) if chiral => T,
+                (12, 6) => Td,
+                (12, _) => Th,
+                (24, _) if chiral => O,
+                (24, _) => Oh,
+                (_, _) if chiral => I,
+                _ => Ih,
+            }
+        };
+
+        Symmetry {
+            group,
+            vertex_orbits: orbits(
+                self.vertices.iter().copied(),
+                |v, a| a.vertices[&v],
+                &automorphisms,
+            ),
+            edge_orbits: orbits(self.edges.iter().copied(), |e, a| a.edge(e), &automorphisms),
+            face_orbits: orbits(0..self.cycles.len(), |i, a| a.faces[i], &automorphisms),
+        }
+    }
+}
+
+/// Group things together when an automorphism takes one to the other
+fn orbits<T: Copy + Eq + std::hash::Hash + Ord>(
+    things: impl Iterator<Item = T>,
+    image: impl Fn(T, &Automorphism) -> T,
+    automorphisms: &[Automorphism],
+) -> Vec<Vec<T>> {
+    let mut things = things.collect::<Vec<_>>();
+    things.sort();
+    let mut seen = HashSet::default();
+    let mut orbits = vec![];
+    for thing in things {
+        if seen.contains(&thing) {
+            continue;
+        }
+        let mut orbit: Vec<T> = automorphisms
+            .iter()
+            .map(|a| image(thing, a))
+            .chain([thing])
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        orbit.sort();
+        seen.extend(orbit.iter().copied());
+        orbits.push(orbit);
+    }
+    orbits
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    a / x * b
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bones::{Operation, PointGroup, PolyGraph};
+    use test_case::test_case;
+
+    #[test_case("T", 24, PointGroup::Td; "T")]
+    #[test_case("C", 48, PointGroup::Oh; "C")]
+    #[test_case("O", 48, PointGroup::Oh; "O")]
+    #[test_case("D", 120, PointGroup::Ih; "D")]
+    #[test_case("I", 120, PointGroup::Ih; "I")]
+    #[test_case("tT", 24, PointGroup::Td; "tT")]
+    #[test_case("gC", 24, PointGroup::O; "gC")]
+    #[test_case("gD", 60, PointGroup::I; "gD")]
+    #[test_case("gT", 120, PointGroup::Ih; "gT")]
+    #[test_case("P5", 20, PointGroup::Dh(5); "P5")]
+    #[test_case("A4", 16, PointGroup::Dd(4); "A4")]
+    #[test_case("Y6", 12, PointGroup::Cv(6); "Y6")]
+    #[test_case("Y4", 8, PointGroup::Cv(4); "Y4")]
+    #[test_case("P3", 12, PointGroup::Dh(3); "P3")]
+    #[test_case("kY4", 8, PointGroup::Cv(4); "kY4")]
+    #[test_case("gP5", 10, PointGroup::D(5); "gP5")]
+    #[test_case("gkY4", 4, PointGroup::C(4); "gkY4")]
+    #[test_case("A6", 24, PointGroup::Dd(6); "A6")]
+    fn point_group(notation: &str, order: usize, group: PointGroup) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        assert_eq!(graph.automorphisms().len(), order);
+        assert_eq!(graph.combinatorial_symmetry().group, group);
+    }
+
+    #[test]
+    fn operations() {
+        let graph: PolyGraph = "C".parse().unwrap();
+        let operations: Vec<Operation> = graph
+            .automorphisms()
+            .iter()
+            .map(|a| a.operation(&graph))
+            .collect();
+        let count = |operation| operations.iter().filter(|&&o| o == operation).count();
+        assert_eq!(count(Operation::Identity), 1);
+        assert_eq!(count(Operation::Rotation(4)), 6);
+        assert_eq!(count(Operation::Rotation(3)), 8);
+        assert_eq!(count(Operation::Rotation(2)), 9);
+        assert_eq!(count(Operation::Inversion), 1);
+        assert_eq!(count(Operation::Reflection), 9);
+        assert_eq!(count(Operation::Rotoreflection(6)), 8);
+        assert_eq!(count(Operation::Rotoreflection(4)), 6);
+    }
+
+    #[test_case("C", 1, 1, 1; "C")]
+    #[test_case("aC", 1, 1, 2; "aC")]
+    #[test_case("tI", 1, 2, 2; "tI")]
+    #[test_case("P5", 1, 2, 2; "P5")]
+    #[test_case("Y4", 2, 2, 2; "Y4")]
+    #[test_case("jC", 2, 1, 1; "jC")]
+    fn orbits(notation: &str, vertices: usize, edges: usize, faces: usize) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        let symmetry = graph.combinatorial_symmetry();
+        assert_eq!(symmetry.vertex_orbits.len(), vertices);
+        assert_eq!(symmetry.edge_orbits.len(), edges);
+        assert_eq!(symmetry.face_orbits.len(), faces);
+        assert_eq!(
+            symmetry.face_orbits.iter().map(Vec::len).sum::<usize>(),
+            graph.cycles.len()
+        );
+    }
+
+    /// Found once for each structure
+    #[test]
+    fn cached() {
+        let mut graph: PolyGraph = "P5".parse().unwrap();
+        assert!(graph.symmetry.get().is_none());
+        assert_eq!(graph.combinatorial_symmetry().face_orbits.len(), 2);
+        assert!(graph.symmetry.get().is_some());
+        graph.truncate(None).unwrap();
+        assert!(graph.symmetry.get().is_none());
+        assert_eq!(graph.combinatorial_symmetry().face_orbits.len(), 3);
+    }
+
+    /// The snub cube has two kinds of triangle, told apart from each other and the squares
+    #[test]
+    fn face_orbit_numbers() {
+        let graph: PolyGraph = "dgC".parse().unwrap();
+        let numbers = graph.combinatorial_symmetry().face_orbit_numbers(&graph);
+        let mut kinds = graph
+            .cycles
+            .iter()
+            .zip(numbers.iter())
+            .map(|(cycle, &number)| (number, cycle.len()))
+            .collect::<Vec<_>>();
+        kinds.sort();
+        kinds.dedup();
+        assert_eq!(kinds, [(0, 3), (1, 3), (2, 4)]);
+        let count = |n| numbers.iter().filter(|&&number| number == n).count();
+        assert_eq!((count(0), count(1), count(2)), (8, 24, 6));
+    }
+
+    #[test_case(PointGroup::Ch(1), "Cs"; "Cs")]
+    #[test_case(PointGroup::S(2), "Ci"; "Ci")]
+    #[test_case(PointGroup::Dh(5), "D5h"; "D5h")]
+    #[test_case(PointGroup::Ih, "Ih"; "Ih")]
+    fn display(group: PointGroup, name: &str) {
+        assert_eq!(group.to_string(), name);
+    }
+}
@@ -7,10 +7,10 @@ pub enum Transaction {
     Contraction(HashSet<Edge>),
     Release(HashSet<Edge>),
     Conway(ConwayMessage),
-    #[allow(dead_code)]
-    ShortenName(usize),
-    Name(char),
+    Name(String),
     Wait(Instant),
+    /// End of an operation the history has a record of, by its number
+    Recorded(usize),
     #[allow(dead_code)]
     None,
 }
@@ -0,0 +1,162 @@
+use crate::bones::{Edge, PolyGraph, PolyGraphError};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+
+// Consistency checks
+impl PolyGraph {
+    /// Check that the graph is a closed polyhedron: every face closes up along edges,
+    /// every edge lies between exactly two faces, and `V - E + F = 2`
+    pub fn validate(&self) -> Result<(), PolyGraphError> {
+        use PolyGraphError::*;
+        let mut sides: HashMap<Edge, usize> = HashMap::default();
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let distinct = cycle.iter().collect::<HashSet<_>>();
+            if cycle.len() < 3 || distinct.len() < cycle.len() {
+                return Err(DegenerateFace(i));
+            }
+            for j in 0..cycle.len() {
+                let (v, u) = (cycle[j], cycle[(j + 1) % cycle.len()]);
+                if !self.vertices.contains(&v) {
+                    return Err(MissingVertex(v));
+                }
+                let e: Edge = (v, u).into();
+                if !self.edges.contains(&e) {
+                    return Err(MissingEdge(v, u));
+                }
+                *sides.entry(e).or_default() += 1;
+            }
+        }
+
+        let mut connected = HashSet::default();
+        for e in self.edges.iter() {
+            for v in [e.v(), e.u()] {
+                if !self.vertices.contains(&v) {
+                    return Err(MissingVertex(v));
+                }
+                connected.insert(v);
+            }
+            match sides.get(e).copied().unwrap_or_default() {
+                2 => {}
+                0 | 1 => return Err(OpenEdge(e.v(), e.u())),
+                _ => return Err(NonManifoldEdge(e.v(), e.u())),
+            }
+        }
+        if let Some(&v) = self.vertices.iter().find(|v| !connected.contains(v)) {
+            return Err(OrphanVertex(v));
+        }
+
+        let euler = self.vertices.len() as i64 - self.edges.len() as i64 + self.cycles.len() as i64;
+        if euler != 2 {
+            return Err(EulerCharacteristic(euler));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bones::{Face, PolyGraph, PolyGraphError, Transaction},
+        render::message::{ConwayMessage, LayoutMessage},
+    };
+    use strum::IntoEnumIterator;
+    use test_case::test_case;
+    use ultraviolet::Vec3;
+
+    #[test_case("T"; "T")]
+    #[test_case("tI"; "tI")]
+    #[test_case("bC"; "bC")]
+    #[test_case("jA5"; "jA5")]
+    #[test_case("GC(2,1)D"; "GC(2,1)D")]
+    fn valid(notation: &str) {
+        let graph: PolyGraph = notation.parse().unwrap();
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn invalid() {
+        let cube = PolyGraph::prism(4);
+
+        let mut graph = cube.clone();
+        graph.cycles[0] = Face::new(vec![0, 1]);
+        assert_eq!(graph.validate(), Err(PolyGraphError::DegenerateFace(0)));
+
+        let mut graph = cube.clone();
+        graph.cycles.remove(0);
+        assert!(matches!(
+            graph.validate(),
+            Err(PolyGraphError::OpenEdge(..))
+        ));
+
+        let mut graph = cube.clone();
+        graph.cycles.push(graph.cycles[0].clone());
+        assert!(matches!(
+            graph.validate(),
+            Err(PolyGraphError::NonManifoldEdge(..))
+        ));
+
+        let mut graph = cube.clone();
+        let e = *graph.edges.iter().next().unwrap();
+        graph.edges.remove(&e);
+        assert!(matches!(
+            graph.validate(),
+            Err(PolyGraphError::MissingEdge(..))
+        ));
+
+        let mut graph = cube.clone();
+        graph.vertices.insert(8);
+        assert_eq!(graph.validate(), Err(PolyGraphError::OrphanVertex(8)));
+    }
+
+    /// Operations that fail, or leave a broken graph behind, are undone
+    #[test_case("aC", Transaction::Conway(ConwayMessage::GoldbergCoxeter(2, 1)); "unsupported")]
+    #[test_case("P4", Transaction::Release([(0, 1).into(), (1, 2).into()].into_iter().collect()); "degenerate")]
+    fn rollback(notation: &str, transaction: Transaction) {
+        let mut graph: PolyGraph = notation.parse().unwrap();
+        let before = graph.clone();
+        graph.transactions = vec![
+            transaction,
+            Transaction::Recorded(0),
+            Transaction::Conway(ConwayMessage::Truncate),
+            Transaction::Recorded(1),
+        ];
+        assert!(graph.update(&LayoutMessage::Spring, 10.0, 0.0).is_err());
+        assert_eq!(graph.vertices, before.vertices);
+        assert_eq!(graph.edges, before.edges);
+        assert_eq!(graph.cycles, before.cycles);
+        assert_eq!(graph.name, before.name);
+        assert!(graph.transactions.is_empty());
+        // Along with the operations queued after it
+        assert_eq!(graph.rolled_back, vec![0, 1]);
+    }
+
+    /// Every step of every operation's animation passes validation
+    #[test]
+    fn animated() {
+        // The one Goldberg-Coxeter operation listed has no arguments
+        for conway in ConwayMessage::iter().filter(|c| c.notation() != "GC(0,0)") {
+            let mut graph = PolyGraph::prism(4);
+            graph.transactions = vec![Transaction::Conway(conway.clone())];
+            while let Some(transaction) = graph.transactions.first() {
+                match transaction {
+                    // Skip pauses rather than sleeping through them
+                    Transaction::Wait(_) => {
+                        graph.transactions.remove(0);
+                        continue;
+                    }
+                    // Pull edges together rather than waiting for the springs to
+                    Transaction::Contraction(edges) => {
+                        for e in edges.clone() {
+                            graph.positions.insert(e.v(), Vec3::zero());
+                            graph.positions.insert(e.u(), Vec3::zero());
+                        }
+                    }
+                    _ => {}
+                }
+                graph
+                    .process_transactions(10.0)
+                    .unwrap_or_else(|error| panic!("{conway}: {error}"));
+            }
+            assert_eq!(graph.validate(), Ok(()));
+        }
+    }
+}
@@ -2,7 +2,7 @@ use iced_wgpu::{graphics::Viewport, wgpu, Engine, Renderer};
 use iced_winit::{
     clipboard::Clipboard,
     conversion::{cursor_position, mouse_interaction, window_event},
-    core::{mouse, renderer, Font, Pixels, Size, Theme},
+    core::{keyboard, mouse, renderer, Event, Font, Pixels, Size, Theme},
     runtime::{program, Debug},
     winit::{
         application::ApplicationHandler,
@@ -164,6 +164,8 @@ pub struct AppData {
     scene: Scene,
     state: program::State<Controls>,
     cursor: Option<PhysicalPosition<f64>>,
+    /// Modifier keys held down, which winit reports separately from key presses
+    modifiers: ModifiersState,
     debug: Debug,
 }
 
@@ -253,8 +255,11 @@ impl App<'_> {
                 0
             } as u32;
 
+            // Schlegel diagrams and nets are seen from inside and behind
+            let cull = !program.state.render.schlegel && program.state.render.unfold.is_none();
+
             // Draw the scene
-            scene.draw(starting_vertex, &mut render_pass);
+            scene.draw(starting_vertex, cull, &mut render_pass);
         }
 
         // And then iced on top
@@ -304,6 +309,7 @@ impl ApplicationHandler for App<'_> {
             scene,
             state,
             cursor: None,
+            modifiers: ModifiersState::default(),
             debug,
         });
     }
@@ -351,53 +357,16 @@ impl ApplicationHandler for App<'_> {
                 WindowEvent::CloseRequested => {
                     event_loop.exit();
                 }
-                WindowEvent::KeyboardInput { event, .. } => {
-                    if event.state.is_pressed() {
-                        let Some(key) = &event.text else {
-                            return;
-                        };
-
-                        let message = if key.as_str() == key.to_uppercase().as_str() {
-                            use PresetMessage::*;
-                            match key.to_lowercase().as_str() {
-                                // Presets
-                                "t" => Some(Pyramid(3)),
-                                "c" => Some(Prism(4)),
-                                "o" => Some(Octahedron),
-                                "d" => Some(Dodecahedron),
-                                "i" => Some(Icosahedron),
-                                _ => None,
-                            }
-                            .map(PolybladeMessage::Preset)
-                        } else {
-                            use ConwayMessage::*;
-                            match key.as_str() {
-                                // Operations
-                                "e" => Some(Expand),
-                                "d" => Some(Dual),
-                                "s" => Some(Snub),
-                                "k" => Some(Kis),
-                                "j" => Some(Join),
-                                "a" => Some(Ambo),
-                                "t" => Some(Truncate),
-                                "b" => Some(Bevel),
-                                _ => None,
-                            }
-                            .map(PolybladeMessage::Conway)
-                        };
-
-                        if let (Some(message), Some(AppData { state, .. })) =
-                            (message, &mut self.data)
-                        {
-                            state.queue_message(message);
-                        }
-                    }
-                }
                 WindowEvent::CursorMoved { position, .. } => {
                     if let Some(AppData { cursor, .. }) = &mut self.data {
                         *cursor = Some(*position);
                     }
                 }
+                WindowEvent::ModifiersChanged(new_modifiers) => {
+                    if let Some(AppData { modifiers, .. }) = &mut self.data {
+                        *modifiers = new_modifiers.state();
+                    }
+                }
                 _ => {}
             }
         }
@@ -405,6 +374,7 @@ impl ApplicationHandler for App<'_> {
         let Some(AppData {
             state,
             cursor,
+            modifiers,
             debug,
             ..
         }) = &mut self.data
@@ -413,18 +383,15 @@ impl ApplicationHandler for App<'_> {
         };
 
         // Map window event to iced event
-        if let Some(event) = window_event(
-            event,
-            self.graphics.window().scale_factor(),
-            ModifiersState::default(),
-        ) {
+        if let Some(event) = window_event(event, self.graphics.window().scale_factor(), *modifiers)
+        {
             state.queue_event(event);
         }
 
         // If there are events pending
         if !state.is_queue_empty() {
             // We update iced
-            let _ = state.update(
+            let (uncaptured, _) = state.update(
                 self.graphics.viewport.logical_size(),
                 cursor
                     .map(|p| cursor_position(p, self.graphics.viewport.scale_factor()))
@@ -437,8 +404,60 @@ impl ApplicationHandler for App<'_> {
                 debug,
             );
 
+            // Keys which no widget captured are shortcuts
+            for event in uncaptured {
+                if let Some(message) = shortcut(&event) {
+                    state.queue_message(message);
+                }
+            }
+
             // and request a redraw
             self.graphics.window.request_redraw();
         }
     }
 }
+
+/// Uppercase letters are seeds and lowercase letters are operations,
+/// while Ctrl+Z and Ctrl+Shift+Z step through the history
+fn shortcut(event: &Event) -> Option<PolybladeMessage> {
+    let Event::Keyboard(keyboard::Event::KeyPressed {
+        key,
+        modifiers,
+        text,
+        ..
+    }) = event
+    else {
+        return None;
+    };
+
+    if modifiers.command() {
+        return match key.as_ref() {
+            keyboard::Key::Character(c) if c.eq_ignore_ascii_case("z") => {
+                Some(if modifiers.shift() {
+                    PolybladeMessage::Redo
+                } else {
+                    PolybladeMessage::Undo
+                })
+            }
+            _ => None,
+        };
+    }
+    if modifiers.alt() {
+        return None;
+    }
+
+    let mut chars = text.as_ref()?.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return None;
+    };
+
+    if c.is_uppercase() {
+        PresetMessage::try_from(c)
+            .ok()
+            .map(PolybladeMessage::Preset)
+    } else {
+        ConwayMessage::try_from(c)
+            .ok()
+            .map(PolybladeMessage::Conway)
+    }
+}
@@ -1,7 +1,7 @@
 use iced::{alignment::Vertical, Length};
-use iced_aw::{menu::Item, menu_bar};
+use iced_aw::{color_picker, menu::Item, menu_bar};
 use iced_wgpu::Renderer;
-use iced_widget::{button, column, container, row, text, Row};
+use iced_widget::{button, column, container, row, text, text_input, Row};
 use iced_winit::{
     core::{Color, Element, Theme},
     runtime::{Program, Task},
@@ -25,6 +25,65 @@ impl Controls {
     }
 }
 
+impl Controls {
+    /// Sizes and angles of the polyhedron as it is laid out right now
+    fn measurements(&self) -> Element<'_, PolybladeMessage, Theme, Renderer> {
+        let m = self.state.model.polyhedron.measure();
+        let degrees = |angle: f32| format!("{:.2}°", angle.to_degrees());
+        container(
+            row![
+                column![
+                    text("Volume:"),
+                    text("Area:"),
+                    text("Face areas:"),
+                    text("Planarity:"),
+                    text("Dihedral angles:"),
+                    text("Edge lengths:"),
+                    text("Radii:"),
+                ],
+                column![
+                    text(format!("{:.4}", m.volume)),
+                    text(format!("{:.4}", m.area)),
+                    text(format!(
+                        "{:.4} to {:.4}",
+                        m.face_areas
+                            .iter()
+                            .copied()
+                            .reduce(f32::min)
+                            .unwrap_or_default(),
+                        m.face_areas
+                            .iter()
+                            .copied()
+                            .reduce(f32::max)
+                            .unwrap_or_default()
+                    )),
+                    text(format!("{:.2e}", m.planarity)),
+                    text(format!(
+                        "{} to {}, {} distinct",
+                        degrees(m.min_dihedral()),
+                        degrees(m.max_dihedral()),
+                        m.dihedral_angles.len()
+                    )),
+                    text(format!(
+                        "{:.4} to {:.4}, mean {:.4}",
+                        m.shortest_edge, m.longest_edge, m.mean_edge
+                    )),
+                    text(format!(
+                        "circum {:.4}, mid {:.4}, in {:.4}",
+                        m.circumradius, m.midradius, m.inradius
+                    )),
+                ]
+            ]
+            .spacing(20),
+        )
+        .style(|_| container::Style {
+            text_color: Some(iced::Color::BLACK),
+            ..Default::default()
+        })
+        .into()
+    }
+}
+
 impl Program for Controls {
     type Renderer = Renderer;
     type Theme = Theme;
@@ -34,18 +93,10 @@ impl Program for Controls {
         message.process(&mut self.state)
     }
 
-    fn view(&self) -> Element<Self::Message, Self::Theme, Self::Renderer> {
+    fn view(&self) -> Element<'_, Self::Message, Self::Theme, Self::Renderer> {
+        let picker = &self.state.render.picker;
         let mut button_row = Row::new().spacing(10);
-        for (i, color) in self
-            .state
-            .render
-            .picker
-            .palette
-            .colors
-            .iter()
-            .cloned()
-            .enumerate()
-        {
+        for (i, color) in picker.palette.colors.iter().cloned().enumerate() {
             button_row = button_row.push(
                 button("")
                     .style(move |theme, status| {
@@ -59,22 +110,53 @@ impl Program for Controls {
             );
         }
 
+        // There are no files to read or write on the web
+        #[cfg(not(target_arch = "wasm32"))]
+        let menus = menu_bar!((PresetMessage::title(), PresetMessage::menu(&()))(
+            ConwayMessage::title(),
+            ConwayMessage::menu(&())
+        )(
+            RenderMessage::title(),
+            RenderMessage::menu(&self.state.render)
+        )(PaletteMessage::title(), PaletteMessage::menu(&()))(
+            ImportMessage::title(),
+            ImportMessage::menu(&())
+        )(ExportMessage::title(), ExportMessage::menu(&())));
+        #[cfg(target_arch = "wasm32")]
+        let menus = menu_bar!((PresetMessage::title(), PresetMessage::menu(&()))(
+            ConwayMessage::title(),
+            ConwayMessage::menu(&())
+        )(
+            RenderMessage::title(),
+            RenderMessage::menu(&self.state.render)
+        )(PaletteMessage::title(), PaletteMessage::menu(&())));
+
         let menu_bar = row![
-            menu_bar!((PresetMessage::title(), PresetMessage::menu(&()))(
-                ConwayMessage::title(),
-                ConwayMessage::menu(&())
-            )(
-                RenderMessage::title(),
-                RenderMessage::menu(&self.state.render)
-            ))
-            .style(iced_aw::menu::primary)
+            menus.style(iced_aw::menu::primary),
+            text_input("Notation", &self.state.notation)
+                .on_input(PolybladeMessage::Notation)
+                .on_submit(PolybladeMessage::SubmitNotation)
+                .width(200),
+            text(self.state.error.clone().unwrap_or_default()).color(Color::BLACK),
+            text(self.state.warning.clone().unwrap_or_default())
+                .color(Color::from_rgb(0.6, 0.3, 0.0))
         ]
         .spacing(10.0);
 
         container(
             column![
                 menu_bar.align_y(Vertical::Top),
-                button_row,
+                color_picker(
+                    picker.color_index.is_some(),
+                    picker.picked_color,
+                    button_row,
+                    Self::Message::Render(RenderMessage::ColorPicker(
+                        ColorPickerMessage::CancelColor
+                    )),
+                    |color| Self::Message::Render(RenderMessage::ColorPicker(
+                        ColorPickerMessage::SubmitColor(color)
+                    )),
+                ),
                 iced_widget::Space::new(Length::Fill, Length::Fill),
                 button(text(self.state.info.name())).on_press(self.state.info.wiki_message()),
                 container(
@@ -85,6 +167,8 @@ impl Program for Controls {
                             text("Faces:"),
                             text("Edges:"),
                             text("Vertices:"),
+                            text("Symmetry:"),
+                            text("Orbits:"),
                         ],
                         column![
                             text(self.state.info.bowers()),
@@ -92,6 +176,8 @@ impl Program for Controls {
                             text(self.state.info.faces),
                             text(self.state.info.edges),
                             text(self.state.info.vertices),
+                            text(self.state.info.symmetry()),
+                            text(self.state.info.orbits()),
                         ]
                     ]
                     .spacing(20)
@@ -102,8 +188,15 @@ impl Program for Controls {
                         text_color: Some(iced::Color::BLACK),
                         ..Default::default()
                     }
-                })
+                }),
+                button(text(if self.state.measuring {
+                    "Measurements ▾"
+                } else {
+                    "Measurements ▸"
+                }))
+                .on_press(PolybladeMessage::Measure(!self.state.measuring)),
             ]
+            .push_maybe(self.state.measuring.then(|| self.measurements()))
             .spacing(10),
         )
         .padding(10)
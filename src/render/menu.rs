@@ -1,6 +1,10 @@
 use crate::render::{
     controls::Controls,
-    message::{ColorMethodMessage, ConwayMessage, PolybladeMessage, PresetMessage, RenderMessage},
+    message::{
+        ColorMethodMessage, ColorPickerMessage, ConwayMessage, ExportMessage, ImportMessage,
+        LayoutMessage, PaletteMessage, PolybladeMessage, PresetMessage, RenderMessage,
+    },
+    palette::{BuiltinPalette, PaletteFormat},
     state::RenderState,
 };
 use iced::{
@@ -140,7 +144,18 @@ impl MenuAble<'static, Controls> for ConwayMessage {
             <Controls as Program>::Renderer,
         >,
     > {
-        ConwayMessage::iter().map(Self::button).collect()
+        use ConwayMessage::*;
+        ConwayMessage::iter()
+            .filter(|conway| !matches!(conway, GoldbergCoxeter(..)))
+            .map(Self::button)
+            .chain([Self::submenu(
+                "Goldberg-Coxeter",
+                [(1, 1), (2, 0), (2, 1), (3, 0), (3, 1), (2, 2), (4, 0)]
+                    .into_iter()
+                    .map(|(m, n)| GoldbergCoxeter(m, n))
+                    .collect(),
+            )])
+            .collect()
     }
 }
 
@@ -166,6 +181,13 @@ impl MenuAble<'static, Controls> for RenderMessage {
         vec![
             Self::checkbox("Schlegel", state.schlegel, Schlegel),
             Self::checkbox("Rotating", state.rotating, Rotating),
+            Self::checkbox("Unfold", state.unfold.is_some(), Unfold),
+            Self::slider(
+                0.0..=1.0,
+                state.unfold.as_ref().map_or(0.0, |unfold| unfold.amount),
+                UnfoldAmount,
+                0.01,
+            ),
             Self::slider(0.0..=10.0, state.line_thickness, LineThickness, 1.0),
             Self::slider(1.0..=5.0, state.zoom, ZoomChanged, 0.05),
             Self::slider(5.0..=50.0, state.speed, SpeedChanged, 10.0),
@@ -175,10 +197,96 @@ impl MenuAble<'static, Controls> for RenderMessage {
                 FovChanged,
                 0.1,
             ),
+            // Number of palette colors a proper coloring spreads its faces over
+            Self::slider(
+                1.0..=state.picker.palette.colors.len() as f32,
+                state.picker.colors as f32,
+                |colors| ColorPicker(ColorPickerMessage::ChangeNumber(colors as i16)),
+                1.0,
+            ),
             Self::submenu(
                 "Color Method",
                 ColorMethodMessage::iter().map(ColorMethod).collect(),
             ),
+            Self::submenu("Layout", LayoutMessage::iter().map(Layout).collect()),
         ]
     }
 }
+
+impl MenuAble<'static, Controls> for ImportMessage {
+    type State = ();
+    const TITLE: &'static str = "Import";
+
+    fn transform(message: Self) -> <Controls as Program>::Message {
+        PolybladeMessage::Import(message)
+    }
+
+    fn menu_items(
+        _: &(),
+    ) -> Vec<
+        Item<
+            'static,
+            <Controls as Program>::Message,
+            <Controls as Program>::Theme,
+            <Controls as Program>::Renderer,
+        >,
+    > {
+        ImportMessage::iter().map(Self::button).collect()
+    }
+}
+
+impl MenuAble<'static, Controls> for ExportMessage {
+    type State = ();
+    const TITLE: &'static str = "Export";
+
+    fn transform(message: Self) -> <Controls as Program>::Message {
+        PolybladeMessage::Export(message)
+    }
+
+    fn menu_items(
+        _: &(),
+    ) -> Vec<
+        Item<
+            'static,
+            <Controls as Program>::Message,
+            <Controls as Program>::Theme,
+            <Controls as Program>::Renderer,
+        >,
+    > {
+        ExportMessage::iter().map(Self::button).collect()
+    }
+}
+
+impl MenuAble<'static, Controls> for PaletteMessage {
+    type State = ();
+    const TITLE: &'static str = "Palette";
+
+    fn transform(message: Self) -> <Controls as Program>::Message {
+        PolybladeMessage::Palette(message)
+    }
+
+    fn menu_items(
+        _: &(),
+    ) -> Vec<
+        Item<
+            'static,
+            <Controls as Program>::Message,
+            <Controls as Program>::Theme,
+            <Controls as Program>::Renderer,
+        >,
+    > {
+        use PaletteMessage::*;
+        let mut items = vec![
+            Self::submenu("Built-in", BuiltinPalette::iter().map(Builtin).collect()),
+            AddSwatch.button(),
+            RemoveSwatch.button(),
+        ];
+        // There are no files to read or write on the web
+        #[cfg(not(target_arch = "wasm32"))]
+        items.extend([
+            Load.button(),
+            Self::submenu("Save", PaletteFormat::iter().map(Save).collect()),
+        ]);
+        items
+    }
+}
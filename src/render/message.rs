@@ -4,21 +4,33 @@ use crate::{
     Instant,
 };
 use iced::{Color, Task};
-use std::fmt::Display;
+use std::{fmt::Display, path::PathBuf};
+use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter};
 
-use crate::render::state::{AppState, ColorPickerState, ModelState, RenderState};
+use crate::render::{
+    palette::{BuiltinPalette, Palette, PaletteFormat},
+    state::{AppState, ColorPickerState, ModelState, RenderState, UnfoldState},
+};
 
 #[derive(Debug, Clone, Display)]
 pub enum PolybladeMessage {
     Tick(Instant),
     Preset(PresetMessage),
     Conway(ConwayMessage),
+    Undo,
+    Redo,
+    Notation(String),
+    SubmitNotation,
     Render(RenderMessage),
+    Import(ImportMessage),
+    Export(ExportMessage),
     OpenWiki(String),
+    Measure(bool),
+    Palette(PaletteMessage),
 }
 
-#[derive(Debug, Clone, EnumIter)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumIter)]
 pub enum PresetMessage {
     Prism(usize),
     AntiPrism(usize),
@@ -42,7 +54,6 @@ impl Display for PresetMessage {
                 _ => "?",
             },
             AntiPrism(n) => match n {
-                2 => "Digonal",
                 3 => "Triangular",
                 4 => "Square",
                 5 => "Pentagonal",
@@ -66,39 +77,128 @@ impl Display for PresetMessage {
     }
 }
 
-#[derive(Debug, Clone, EnumIter, Display)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumIter, Display)]
 pub enum ConwayMessage {
     // 1
     Dual,
+    Reflect,
     // 2
     Join,
     Ambo,
     // 3
     Kis,
-    // Needle,
-    // Zip,
+    Needle,
+    Zip,
     Truncate,
     // 4
-    //Ortho,
+    Ortho,
     Expand,
+    Chamfer,
+    Subdivide,
     // 5
-    // Gyro,
+    Gyro,
     Snub,
-    // // 6
-    // Meta,
+    Loft,
+    Propeller,
+    // 6
+    Meta,
     Bevel,
+    Quinto,
+    JoinLace,
+    // 7
+    Whirl,
+    // m² + mn + n²
+    #[strum(to_string = "GoldbergCoxeter({0}, {1})")]
+    GoldbergCoxeter(usize, usize),
+}
+
+impl ConwayMessage {
+    /// Representation of this operation in Conway Polyhedron Notation
+    pub fn notation(&self) -> String {
+        use ConwayMessage::*;
+        match self {
+            Dual => "d".into(),
+            Join => "j".into(),
+            Ambo => "a".into(),
+            Kis => "k".into(),
+            Needle => "n".into(),
+            Zip => "z".into(),
+            Truncate => "t".into(),
+            Ortho => "o".into(),
+            Expand => "e".into(),
+            Chamfer => "c".into(),
+            Subdivide => "u".into(),
+            Gyro => "g".into(),
+            Snub => "s".into(),
+            Meta => "m".into(),
+            Bevel => "b".into(),
+            Reflect => "r".into(),
+            Loft => "l".into(),
+            Propeller => "p".into(),
+            Whirl => "w".into(),
+            Quinto => "q".into(),
+            JoinLace => "L0".into(),
+            GoldbergCoxeter(m, n) => format!("GC({m},{n})"),
+        }
+    }
+}
+
+impl TryFrom<char> for ConwayMessage {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        ConwayMessage::iter()
+            .find(|conway| conway.notation() == value.to_string())
+            .ok_or(value)
+    }
+}
+
+impl PresetMessage {
+    /// Representation of this seed in Conway Polyhedron Notation
+    pub fn notation(&self) -> String {
+        use PresetMessage::*;
+        match self {
+            Prism(4) => "C".into(),
+            Pyramid(3) => "T".into(),
+            Prism(n) => format!("P{n}"),
+            AntiPrism(n) => format!("A{n}"),
+            Pyramid(n) => format!("Y{n}"),
+            Octahedron => "O".into(),
+            Dodecahedron => "D".into(),
+            Icosahedron => "I".into(),
+        }
+    }
+}
+
+impl TryFrom<char> for PresetMessage {
+    type Error = char;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        use PresetMessage::*;
+        match value {
+            'T' => Ok(Pyramid(3)),
+            'C' => Ok(Prism(4)),
+            'O' => Ok(Octahedron),
+            'D' => Ok(Dodecahedron),
+            'I' => Ok(Icosahedron),
+            _ => Err(value),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum RenderMessage {
     Schlegel(bool),
     Rotating(bool),
+    Unfold(bool),
+    UnfoldAmount(f32),
     FovChanged(f32),
     ZoomChanged(f32),
     SpeedChanged(f32),
     LineThickness(f32),
     ColorMethod(ColorMethodMessage),
     ColorPicker(ColorPickerMessage),
+    Layout(LayoutMessage),
 }
 
 impl Display for RenderMessage {
@@ -106,6 +206,7 @@ impl Display for RenderMessage {
         use RenderMessage::*;
         let value = match &self {
             ColorMethod(method) => method.to_string(),
+            Layout(layout) => layout.to_string(),
             _ => {
                 format!("{self:?}")
             }
@@ -120,6 +221,14 @@ pub enum ColorMethodMessage {
     Edge,
     Polygon,
     Face,
+    /// Faces which a symmetry swaps share a color
+    #[strum(to_string = "Symmetry Orbit")]
+    Orbit,
+    /// Faces sharing an edge never share a color, with as few colors as it takes
+    #[strum(to_string = "Proper Coloring")]
+    Proper,
+    /// Faces keep the color of the face they came from through every operation
+    Lineage,
 }
 
 impl From<ColorMethodMessage> for f32 {
@@ -129,6 +238,64 @@ impl From<ColorMethodMessage> for f32 {
             ColorMethodMessage::Edge => 1.0,
             ColorMethodMessage::Polygon => 2.0,
             ColorMethodMessage::Face => 2.0,
+            ColorMethodMessage::Orbit => 2.0,
+            ColorMethodMessage::Proper => 2.0,
+            ColorMethodMessage::Lineage => 2.0,
+        }
+    }
+}
+
+/// How vertex positions are found
+#[derive(Debug, Clone, EnumIter, Display)]
+pub enum LayoutMessage {
+    /// Springs between every pair of vertices, relative to their distance in the graph
+    Spring,
+    /// Planar faces with every edge tangent to the unit sphere
+    Canonical,
+    /// Springs between nearby vertices, with every vertex pushing the rest away,
+    /// for polyhedra too big for `Spring`
+    #[strum(to_string = "Barnes-Hut")]
+    BarnesHut,
+}
+
+/// Files a polyhedron can be imported from
+#[derive(Debug, Clone, EnumIter, Display)]
+pub enum ImportMessage {
+    #[strum(to_string = "OFF/OBJ…")]
+    Mesh,
+}
+
+/// File formats the polyhedron can be exported to
+#[derive(Debug, Clone, EnumIter, Display)]
+pub enum ExportMessage {
+    #[strum(to_string = "OBJ")]
+    Obj,
+    #[strum(to_string = "OFF")]
+    Off,
+    #[strum(to_string = "STL (ASCII)")]
+    StlAscii,
+    #[strum(to_string = "STL (Binary)")]
+    StlBinary,
+    #[strum(to_string = "PLY")]
+    Ply,
+    #[strum(to_string = "Net (SVG)")]
+    NetSvg,
+    #[strum(to_string = "Net with glue tabs (SVG)")]
+    NetSvgTabs,
+    #[strum(to_string = "Measurements (CSV)")]
+    MeasurementsCsv,
+}
+
+impl ExportMessage {
+    pub fn extension(&self) -> &'static str {
+        use ExportMessage::*;
+        match self {
+            Obj => "obj",
+            Off => "off",
+            StlAscii | StlBinary => "stl",
+            Ply => "ply",
+            NetSvg | NetSvgTabs => "svg",
+            MeasurementsCsv => "csv",
         }
     }
 }
@@ -141,7 +308,34 @@ pub enum ColorPickerMessage {
     CancelColor,
 }
 
+/// Changes to the palette, and saving it
+#[derive(Debug, Clone)]
+pub enum PaletteMessage {
+    Builtin(BuiltinPalette),
+    /// Copy the chosen swatch, or the last one
+    AddSwatch,
+    /// Remove the chosen swatch, or the last one
+    RemoveSwatch,
+    /// Replace the palette with one from a file
+    Load,
+    Save(PaletteFormat),
+}
+
+impl Display for PaletteMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use PaletteMessage::*;
+        match self {
+            Builtin(builtin) => builtin.fmt(f),
+            AddSwatch => f.write_str("Add Swatch"),
+            RemoveSwatch => f.write_str("Remove Swatch"),
+            Load => f.write_str("Load…"),
+            Save(format) => f.write_fmt(format_args!("Save ({format})")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, EnumIter, Display)]
+#[allow(dead_code)]
 pub enum ModelMessage {
     ScaleChanged(f32),
 }
@@ -152,26 +346,7 @@ pub trait ProcessMessage<T> {
 
 impl ProcessMessage<ModelState> for PresetMessage {
     fn process(&self, state: &mut ModelState) -> Task<PolybladeMessage> {
-        use PresetMessage::*;
-        match &self {
-            Prism(n) => {
-                state.polyhedron = PolyGraph::prism(*n);
-                if n == &4 {
-                    state.polyhedron.name = "C".into();
-                }
-            }
-            AntiPrism(n) => state.polyhedron = PolyGraph::anti_prism(*n),
-            Pyramid(n) => {
-                state.polyhedron = PolyGraph::pyramid(*n);
-                if n == &3 {
-                    state.polyhedron.name = "T".into();
-                }
-            }
-            Octahedron => state.polyhedron = PolyGraph::octahedron(),
-            Dodecahedron => state.polyhedron = PolyGraph::dodecahedron(),
-            Icosahedron => state.polyhedron = PolyGraph::icosahedron(),
-        }
-
+        state.polyhedron = PolyGraph::preset(self);
         Task::none()
     }
 }
@@ -209,6 +384,19 @@ impl ProcessMessage<RenderState> for RenderMessage {
                 }
                 Task::none()
             }
+            Unfold(unfold) => {
+                state.unfold = unfold.then(|| UnfoldState {
+                    opening: true,
+                    ..Default::default()
+                });
+                Task::none()
+            }
+            UnfoldAmount(amount) => {
+                let unfold = state.unfold.get_or_insert_with(Default::default);
+                unfold.amount = *amount;
+                unfold.opening = false;
+                Task::none()
+            }
             FovChanged(fov) => {
                 state.camera.fov_y = *fov;
                 Task::none()
@@ -230,7 +418,96 @@ impl ProcessMessage<RenderState> for RenderMessage {
                 Task::none()
             }
             ColorPicker(picker) => picker.process(&mut state.picker),
+            Layout(layout) => {
+                state.layout = layout.clone();
+                Task::none()
+            }
+        }
+    }
+}
+
+/// Ask where to save a file, starting from `name` with `extension`.
+/// The dialog asks before replacing a file that's already there.
+#[cfg(not(target_arch = "wasm32"))]
+fn save_path(title: &str, name: &str, extension: &str) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title(title)
+        .set_file_name(format!("{name}.{extension}"))
+        .add_filter(extension, &[extension])
+        .save_file()
+}
+
+/// There is nowhere to write files on the web
+#[cfg(target_arch = "wasm32")]
+fn save_path(_: &str, _: &str, _: &str) -> Option<PathBuf> {
+    None
+}
+
+/// Ask which file to open, showing those of a `kind` with any of the `extensions`
+#[cfg(not(target_arch = "wasm32"))]
+fn open_path(title: &str, kind: &str, extensions: &[&str]) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title(title)
+        .add_filter(kind, extensions)
+        .pick_file()
+}
+
+/// There are no files to read on the web
+#[cfg(target_arch = "wasm32")]
+fn open_path(_: &str, _: &str, _: &[&str]) -> Option<PathBuf> {
+    None
+}
+
+impl ProcessMessage<AppState> for ImportMessage {
+    fn process(&self, state: &mut AppState) -> Task<PolybladeMessage> {
+        let Some(path) = open_path(
+            &format!("Import {self}"),
+            "Mesh",
+            crate::bones::IMPORT_EXTENSIONS,
+        ) else {
+            return Task::none();
+        };
+        match PolyGraph::import(&path) {
+            Ok(polyhedron) => {
+                state.history.record(&state.model.polyhedron);
+                state.model.polyhedron = polyhedron;
+                state.error = None;
+            }
+            Err(error) => {
+                log::warn!("failed to import {path:?}: {error}");
+                state.error = Some(format!("failed to import {}: {error}", path.display()));
+            }
         }
+        Task::none()
+    }
+}
+
+impl ProcessMessage<AppState> for ExportMessage {
+    fn process(&self, state: &mut AppState) -> Task<PolybladeMessage> {
+        let polyhedron = &state.model.polyhedron;
+        let mut colors = state.render.face_colors(polyhedron);
+        // Paper models are always colored, by polygon if nothing else
+        if matches!(self, ExportMessage::NetSvg | ExportMessage::NetSvgTabs) && colors.is_none() {
+            colors = Some(state.render.polygon_colors(polyhedron));
+        }
+        let name = match polyhedron.name.as_str() {
+            "" => "polyhedron",
+            name => name,
+        };
+        let Some(path) = save_path(&format!("Export {self}"), name, self.extension()) else {
+            return Task::none();
+        };
+        match polyhedron.export(self, colors.as_deref(), &path) {
+            Ok(written) => {
+                log::info!("exported {written:?}");
+                state.error = None;
+            }
+            Err(error) => {
+                log::warn!("failed to export {path:?}: {error}");
+                state.error = Some(format!("failed to export {}: {error}", path.display()));
+            }
+        }
+        Task::none()
     }
 }
 
@@ -239,7 +516,7 @@ impl ProcessMessage<ColorPickerState> for ColorPickerMessage {
         use ColorPickerMessage::*;
         match self {
             ChangeNumber(colors) => {
-                state.colors = *colors;
+                state.colors = (*colors).clamp(1, state.palette.colors.len() as i16);
             }
             ChooseColor(i) => {
                 state.color_index = Some(*i);
@@ -260,6 +537,65 @@ impl ProcessMessage<ColorPickerState> for ColorPickerMessage {
     }
 }
 
+impl ProcessMessage<AppState> for PaletteMessage {
+    fn process(&self, app: &mut AppState) -> Task<PolybladeMessage> {
+        use PaletteMessage::*;
+        let state = &mut app.render.picker;
+        match self {
+            Builtin(builtin) => state.switch((*builtin).into()),
+            AddSwatch => {
+                let i = state.color_index.unwrap_or(state.palette.colors.len() - 1);
+                state.palette.colors.insert(i + 1, state.palette.colors[i]);
+                state.color_index = Some(i + 1);
+            }
+            RemoveSwatch => {
+                // Faces always need at least one color
+                if state.palette.colors.len() > 1 {
+                    let i = state.color_index.unwrap_or(state.palette.colors.len() - 1);
+                    state.palette.colors.remove(i);
+                    state.color_index = None;
+                    state.colors = state.colors.min(state.palette.colors.len() as i16);
+                }
+            }
+            Load => {
+                let extensions = PaletteFormat::iter()
+                    .map(|format| format.extension())
+                    .collect::<Vec<_>>();
+                let Some(path) = open_path("Load palette", "Palette", &extensions) else {
+                    return Task::none();
+                };
+                match Palette::load(&path) {
+                    Ok(palette) => {
+                        state.switch(palette);
+                        app.error = None;
+                    }
+                    Err(error) => {
+                        log::warn!("failed to load palette {path:?}: {error}");
+                        app.error = Some(format!("failed to load {}: {error}", path.display()));
+                    }
+                }
+            }
+            Save(format) => {
+                let title = format!("Save palette as {format}");
+                let Some(path) = save_path(&title, &state.palette.name, format.extension()) else {
+                    return Task::none();
+                };
+                match std::fs::write(&path, state.palette.contents(*format)) {
+                    Ok(()) => {
+                        log::info!("saved palette to {path:?}");
+                        app.error = None;
+                    }
+                    Err(error) => {
+                        log::warn!("failed to save palette to {path:?}: {error}");
+                        app.error = Some(format!("failed to save {}: {error}", path.display()));
+                    }
+                }
+            }
+        }
+        Task::none()
+    }
+}
+
 impl ProcessMessage<AppState> for PolybladeMessage {
     fn process(&self, state: &mut AppState) -> Task<PolybladeMessage> {
         use PolybladeMessage::*;
@@ -270,22 +606,115 @@ impl ProcessMessage<AppState> for PolybladeMessage {
                         state.model.polyhedron.face_centroid(0) * state.render.zoom;
                 }
 
-                // If the polyhedron has changed
-                if state.info.conway != state.model.polyhedron.name {
+                state.update_state(*time);
+
+                // If the polyhedron has changed, part way through an operation or otherwise,
+                // or has just stopped changing and its symmetry can be found
+                let polyhedron = &state.model.polyhedron;
+                if state.info.conway != polyhedron.name
+                    || state.info.faces != polyhedron.cycles.len()
+                    || state.info.edges != polyhedron.edges.len()
+                    || state.info.vertices != polyhedron.vertices.len()
+                    || (state.info.symmetry.is_none() && polyhedron.transactions.is_empty())
+                {
                     // Recompute its Polydex entry
-                    state.info = state.model.polyhedron.polydex_entry(&state.polydex);
+                    state.info = polyhedron.polydex_entry(&state.polydex);
+                    state.render.face_orbits = state
+                        .info
+                        .symmetry
+                        .as_ref()
+                        .map(|symmetry| symmetry.face_orbit_numbers(polyhedron))
+                        .unwrap_or_default();
+                    state.render.proper_coloring_stale = true;
+                }
+                // Colorings are only searched for while they are shown, and not while
+                // an operation is running, since the search can take a while
+                let palette = state.render.picker.palette.colors.len();
+                let colors = (state.render.picker.colors.max(1) as usize).min(palette);
+                if !matches!(state.render.method, ColorMethodMessage::Proper) {
+                    state.warning = None;
+                }
+                if matches!(state.render.method, ColorMethodMessage::Proper)
+                    && polyhedron.transactions.is_empty()
+                    && (state.render.proper_coloring_stale
+                        || state
+                            .render
+                            .proper_coloring
+                            .as_ref()
+                            .is_none_or(|(requested, _)| *requested != colors))
+                {
+                    let coloring = polyhedron.face_coloring(colors);
+                    // Faces past the end of the palette would wrap around onto their neighbours' colors
+                    let needed = coloring.iter().max().map_or(0, |c| c + 1);
+                    state.warning = (needed > palette).then(|| {
+                        log::warn!("a proper coloring needs {needed} colors");
+                        format!("a proper coloring needs {needed} colors, but the palette has {palette}")
+                    });
+                    state.render.proper_coloring = Some((colors, coloring));
+                    state.render.proper_coloring_stale = false;
+                }
+                Task::none()
+            }
+            Preset(preset) => {
+                state.history.record(&state.model.polyhedron);
+                preset.process(&mut state.model)
+            }
+            Conway(conway) => {
+                let record = state.history.record(&state.model.polyhedron);
+                state.error = None;
+                let task = conway.process(&mut state.model);
+                // So that the record is forgotten if the operation is rolled back
+                state
+                    .model
+                    .polyhedron
+                    .transactions
+                    .push(Transaction::Recorded(record));
+                task
+            }
+            Undo => {
+                if let Some(polyhedron) = state.history.undo(&state.model.polyhedron) {
+                    state.model.polyhedron = polyhedron;
+                    state.error = None;
+                }
+                Task::none()
+            }
+            Redo => {
+                if let Some(polyhedron) = state.history.redo(&state.model.polyhedron) {
+                    state.model.polyhedron = polyhedron;
+                    state.error = None;
+                }
+                Task::none()
+            }
+            Notation(notation) => {
+                state.notation = notation.clone();
+                Task::none()
+            }
+            SubmitNotation => {
+                match state.notation.parse::<crate::bones::Notation>() {
+                    Ok(notation) => {
+                        state.history.record(&state.model.polyhedron);
+                        state.model.polyhedron = notation.animated();
+                        state.error = None;
+                    }
+                    Err(error) => {
+                        log::warn!("invalid notation {:?}: {error}", state.notation);
+                        state.error = Some(error.to_string());
+                    }
                 }
-
-                state.update_state(*time);
                 Task::none()
             }
-            Preset(preset) => preset.process(&mut state.model),
-            Conway(conway) => conway.process(&mut state.model),
             Render(render) => render.process(&mut state.render),
+            Import(import) => import.process(state),
+            Export(format) => format.process(state),
             OpenWiki(wiki) => {
                 let _ = webbrowser::open(wiki).ok();
                 Task::none()
             }
+            Measure(measuring) => {
+                state.measuring = *measuring;
+                Task::none()
+            }
+            Palette(palette) => palette.process(state),
         }
     }
 }
@@ -1,8 +1,13 @@
 use crate::render::color::RGBA;
 use iced::widget::shader::wgpu;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Write as _, path::Path};
+use strum_macros::{Display, EnumIter};
 
 #[derive(Debug, Clone)]
 pub struct Palette {
+    /// Used to name the file it is saved to
+    pub name: String,
     pub colors: Vec<RGBA>,
 }
 
@@ -16,33 +21,291 @@ impl From<Palette> for Vec<wgpu::Color> {
 }
 
 impl Palette {
-    fn new(colors: &[&str]) -> Self {
+    fn new(name: &str, colors: &[&str]) -> Self {
         Self {
+            name: name.to_string(),
             colors: colors.iter().map(|&c| c.try_into().unwrap()).collect(),
         }
     }
 
     pub fn polyblade() -> Self {
-        Self::new(&[
-            "#48845A", "#A3BA70", "#335145", "#FEF086", "#5F9BFC", "#F4A4E7", "#AA89BE",
-        ])
+        Self::new(
+            "polyblade",
+            &[
+                "#48845A", "#A3BA70", "#335145", "#FEF086", "#5F9BFC", "#F4A4E7", "#AA89BE",
+            ],
+        )
     }
 
     // https://lospec.com/palette-list/desatur8
     pub fn desatur8() -> Self {
-        Self::new(&[
-            "#f0f0eb", "#ffff8f", "#7be098", "#849ad8", "#e8b382", "#d8828e", "#a776c1", "#545155",
-        ])
+        Self::new(
+            "desatur8",
+            &[
+                "#f0f0eb", "#ffff8f", "#7be098", "#849ad8", "#e8b382", "#d8828e", "#a776c1",
+                "#545155",
+            ],
+        )
     }
 
     pub fn clement() -> Self {
-        Self::new(&[
-            "#639bff", "#8854f3", "#ff79ae", "#ff8c5c", "#fff982", "#63ffba",
-        ])
+        Self::new(
+            "clement",
+            &[
+                "#639bff", "#8854f3", "#ff79ae", "#ff8c5c", "#fff982", "#63ffba",
+            ],
+        )
     }
     pub fn dream_haze() -> Self {
-        Self::new(&[
-            "#3c42c4", "#6e51c8", "#a065cd", "#ce79d2", "#d68fb8", "#dda2a3", "#eac4ae", "#f4dfbe",
-        ])
+        Self::new(
+            "dream_haze",
+            &[
+                "#3c42c4", "#6e51c8", "#a065cd", "#ce79d2", "#d68fb8", "#dda2a3", "#eac4ae",
+                "#f4dfbe",
+            ],
+        )
+    }
+}
+
+/// Palettes that come with Polyblade
+#[derive(Debug, Clone, Copy, EnumIter, Display)]
+pub enum BuiltinPalette {
+    Polyblade,
+    Desatur8,
+    Clement,
+    #[strum(to_string = "Dream Haze")]
+    DreamHaze,
+}
+
+impl From<BuiltinPalette> for Palette {
+    fn from(builtin: BuiltinPalette) -> Self {
+        match builtin {
+            BuiltinPalette::Polyblade => Palette::polyblade(),
+            BuiltinPalette::Desatur8 => Palette::desatur8(),
+            BuiltinPalette::Clement => Palette::clement(),
+            BuiltinPalette::DreamHaze => Palette::dream_haze(),
+        }
+    }
+}
+
+/// File formats palettes can be read from and written to
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Display)]
+pub enum PaletteFormat {
+    #[strum(to_string = "RON")]
+    Ron,
+    /// One color to a line, as Lospec exports them
+    #[strum(to_string = "HEX")]
+    Hex,
+    /// GIMP palette
+    #[strum(to_string = "GPL")]
+    Gpl,
+}
+
+impl PaletteFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PaletteFormat::Ron => "ron",
+            PaletteFormat::Hex => "hex",
+            PaletteFormat::Gpl => "gpl",
+        }
+    }
+
+    /// Format a file is in, going by its extension
+    pub fn of(path: &Path) -> Option<Self> {
+        use strum::IntoEnumIterator;
+        let extension = path.extension()?.to_str()?;
+        Self::iter().find(|format| extension.eq_ignore_ascii_case(format.extension()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteError {
+    /// The file couldn't be read or written
+    Io(String),
+    /// Not a file extension we know how to read
+    Format(String),
+    /// No colors in the file
+    Empty,
+    /// A line that doesn't follow the format
+    Syntax { line: usize, message: String },
+}
+
+impl std::fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use PaletteError::*;
+        match self {
+            Io(error) => f.write_str(error),
+            Format(path) => f.write_fmt(format_args!("'{path}' is not a RON, HEX or GPL palette")),
+            Empty => f.write_str("no colors in palette"),
+            Syntax { line, message } => f.write_fmt(format_args!("line {line}: {message}")),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+/// How palettes are laid out in RON
+#[derive(Serialize, Deserialize)]
+struct RonPalette {
+    colors: Vec<String>,
+}
+
+/// Six hex digits, with or without a `#` in front
+fn parse_hex(value: &str) -> Option<RGBA> {
+    let digits = value.trim().trim_start_matches('#');
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    format!("#{digits}").as_str().try_into().ok()
+}
+
+fn hex(color: &RGBA) -> String {
+    format!("{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+impl Palette {
+    /// Read a RON, HEX or GPL palette, named after the file
+    pub fn load(path: &Path) -> Result<Palette, PaletteError> {
+        let format =
+            PaletteFormat::of(path).ok_or(PaletteError::Format(path.display().to_string()))?;
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| PaletteError::Io(e.to_string()))?;
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("palette");
+        Self::parse(name, &contents, format)
+    }
+
+    pub fn parse(name: &str, contents: &str, format: PaletteFormat) -> Result<Self, PaletteError> {
+        let syntax = |line: usize, message: &str| PaletteError::Syntax {
+            line,
+            message: message.to_string(),
+        };
+        let mut lines = contents
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()));
+        let colors = match format {
+            PaletteFormat::Ron => {
+                let ron: RonPalette = ron::from_str(contents)
+                    .map_err(|e| syntax(e.position.line, &e.code.to_string()))?;
+                ron.colors
+                    .iter()
+                    .map(|c| parse_hex(c).ok_or(syntax(1, &format!("'{c}' is not a hex color"))))
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+            PaletteFormat::Hex => lines
+                .filter(|(_, line)| !line.is_empty())
+                .map(|(i, line)| parse_hex(line).ok_or(syntax(i, "expected six hex digits")))
+                .collect::<Result<Vec<_>, _>>()?,
+            PaletteFormat::Gpl => {
+                if lines.next().is_none_or(|(_, line)| line != "GIMP Palette") {
+                    return Err(syntax(1, "expected 'GIMP Palette'"));
+                }
+                lines
+                    .filter(|(_, line)| {
+                        !line.is_empty()
+                            && !line.starts_with('#')
+                            && !line.starts_with("Name:")
+                            && !line.starts_with("Columns:")
+                    })
+                    .map(|(i, line)| {
+                        // Red, green and blue, then the name of the color
+                        let channels = line
+                            .split_whitespace()
+                            .take(3)
+                            .map(str::parse::<u8>)
+                            .collect::<Result<Vec<_>, _>>()
+                            .ok()
+                            .filter(|channels| channels.len() == 3)
+                            .ok_or(syntax(i, "expected red, green and blue from 0 to 255"))?;
+                        Ok(RGBA::new(channels[0], channels[1], channels[2], u8::MAX))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+        if colors.is_empty() {
+            return Err(PaletteError::Empty);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            colors,
+        })
+    }
+
+    /// The palette written out as a file
+    pub fn contents(&self, format: PaletteFormat) -> String {
+        match format {
+            PaletteFormat::Ron => {
+                let ron = RonPalette {
+                    colors: self.colors.iter().map(|c| format!("#{}", hex(c))).collect(),
+                };
+                ron::ser::to_string_pretty(&ron, Default::default()).unwrap_or_default()
+            }
+            PaletteFormat::Hex => self.colors.iter().fold(String::new(), |mut hexes, c| {
+                let _ = writeln!(hexes, "{}", hex(c));
+                hexes
+            }),
+            PaletteFormat::Gpl => {
+                let mut gpl = format!(
+                    "GIMP Palette\nName: {}\nColumns: {}\n#\n",
+                    self.name,
+                    self.colors.len()
+                );
+                for c in self.colors.iter() {
+                    let _ = writeln!(gpl, "{:3} {:3} {:3}\t{}", c.r, c.g, c.b, hex(c));
+                }
+                gpl
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Palette, PaletteError, PaletteFormat};
+    use strum::IntoEnumIterator;
+    use test_case::test_case;
+
+    /// Every format reads back what it writes
+    #[test]
+    fn round_trip() {
+        let palette = Palette::dream_haze();
+        for format in PaletteFormat::iter() {
+            let read = Palette::parse("dream_haze", &palette.contents(format), format).unwrap();
+            assert_eq!(read.colors.len(), palette.colors.len(), "{format}");
+            for (a, b) in read.colors.iter().zip(palette.colors.iter()) {
+                assert_eq!((a.r, a.g, a.b), (b.r, b.g, b.b), "{format}");
+            }
+        }
+    }
+
+    #[test_case(PaletteFormat::Hex, "ff0000\n#00FF00\n\n0000ff\n"; "hex")]
+    #[test_case(PaletteFormat::Gpl, "GIMP Palette\nName: RGB\nColumns: 3\n# Comment\n255   0   0\tRed\n  0 255   0\n  0   0 255 Blue\n"; "gpl")]
+    #[test_case(PaletteFormat::Ron, "(colors: [\"#ff0000\", \"00ff00\", \"#0000FF\"])"; "ron")]
+    fn parse(format: PaletteFormat, contents: &str) {
+        let palette = Palette::parse("rgb", contents, format).unwrap();
+        let colors = palette
+            .colors
+            .iter()
+            .map(|c| (c.r, c.g, c.b))
+            .collect::<Vec<_>>();
+        assert_eq!(colors, [(255, 0, 0), (0, 255, 0), (0, 0, 255)]);
+    }
+
+    #[test_case(PaletteFormat::Hex, "ff0000\nf00\n", Some(2); "short hex")]
+    #[test_case(PaletteFormat::Gpl, "255 0 0\n", Some(1); "missing header")]
+    #[test_case(PaletteFormat::Gpl, "GIMP Palette\n255 0 300\n", Some(2); "out of range")]
+    #[test_case(PaletteFormat::Hex, "\n\n", None; "empty")]
+    fn invalid(format: PaletteFormat, contents: &str, line: Option<usize>) {
+        let error = Palette::parse("broken", contents, format).unwrap_err();
+        match line {
+            Some(line) => assert!(
+                matches!(error, PaletteError::Syntax { line: l, .. } if l == line),
+                "{error}"
+            ),
+            None => assert_eq!(error, PaletteError::Empty),
+        }
     }
 }
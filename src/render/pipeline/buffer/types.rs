@@ -5,14 +5,17 @@ use ultraviolet::{Mat4, Vec3, Vec4};
 pub struct MomentVertex {
     pub position: Vec3,
     pub color: Vec4,
+    /// Colors of the sides of the triangle, each opposite the corner at the same barycentric index
+    pub sides: [Vec4; 3],
     pub _padding: f32,
 }
 
 impl MomentVertex {
-    pub fn new(position: Vec3, color: Vec4) -> MomentVertex {
+    pub fn new(position: Vec3, color: Vec4, sides: [Vec4; 3]) -> MomentVertex {
         Self {
             position,
             color,
+            sides,
             _padding: 0.0,
         }
     }
@@ -16,7 +16,10 @@ pub use texture::Texture;
 
 unsafe impl Send for Scene {}
 pub struct Scene {
+    /// Draws only the outsides of faces
     pipeline: wgpu::RenderPipeline,
+    /// Draws both sides of faces, for views where their insides show
+    open_pipeline: wgpu::RenderPipeline,
     pub moment_buf: Buffer,
     pub shape_buf: Buffer,
     pub model_buf: Buffer,
@@ -31,7 +34,14 @@ impl Scene {
         texture_format: wgpu::TextureFormat,
         size: &Size<u32>,
     ) -> Scene {
-        let pipeline = Self::build_pipeline(device, texture_format);
+        let uniform_layout = Self::uniform_layout(device);
+        let pipeline = Self::build_pipeline(
+            device,
+            texture_format,
+            &uniform_layout,
+            Some(wgpu::Face::Back),
+        );
+        let open_pipeline = Self::build_pipeline(device, texture_format, &uniform_layout, None);
         // Moment and shape
         let moment_buf = Buffer::new::<MomentVertex>(device, "moment", BufferKind::Vertex);
         let shape_buf = Buffer::new::<ShapeVertex>(device, "shape", BufferKind::Vertex);
@@ -41,7 +51,7 @@ impl Scene {
 
         let uniform_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: None,
-            layout: &pipeline.get_bind_group_layout(0),
+            layout: &uniform_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -57,6 +67,7 @@ impl Scene {
 
         Scene {
             pipeline,
+            open_pipeline,
             moment_buf,
             shape_buf,
             model_buf,
@@ -104,20 +115,21 @@ impl Scene {
         })
     }
 
-    pub fn draw<'a>(&'a self, starting_vertex: u32, pass: &mut wgpu::RenderPass<'a>) {
-        pass.set_pipeline(&self.pipeline);
+    /// Draw the faces from `starting_vertex` on, leaving out their insides if `cull` is set
+    pub fn draw<'a>(&'a self, starting_vertex: u32, cull: bool, pass: &mut wgpu::RenderPass<'a>) {
+        pass.set_pipeline(if cull {
+            &self.pipeline
+        } else {
+            &self.open_pipeline
+        });
         pass.set_bind_group(0, &self.uniform_group, &[]);
         pass.set_vertex_buffer(0, self.moment_buf.raw_slice());
         pass.set_vertex_buffer(1, self.shape_buf.raw_slice());
         pass.draw(starting_vertex..self.shape_buf.len() as u32, 0..1);
     }
 
-    fn build_pipeline(
-        device: &wgpu::Device,
-        texture_format: wgpu::TextureFormat,
-    ) -> wgpu::RenderPipeline {
-        let module = &device.create_shader_module(wgpu::include_wgsl!("../shaders/shader.wgsl"));
-        let uniform_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    fn uniform_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: None,
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -141,11 +153,21 @@ impl Scene {
                     count: None,
                 },
             ],
-        });
+        })
+    }
+
+    /// Faces wound counterclockwise on screen are the fronts, and `cull_mode` sides are skipped
+    fn build_pipeline(
+        device: &wgpu::Device,
+        texture_format: wgpu::TextureFormat,
+        uniform_layout: &wgpu::BindGroupLayout,
+        cull_mode: Option<wgpu::Face>,
+    ) -> wgpu::RenderPipeline {
+        let module = &device.create_shader_module(wgpu::include_wgsl!("../shaders/shader.wgsl"));
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
             push_constant_ranges: &[],
-            bind_group_layouts: &[&uniform_layout],
+            bind_group_layouts: &[uniform_layout],
         });
         device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
@@ -162,6 +184,10 @@ impl Scene {
                             0 => Float32x3,
                             // color
                             1 => Float32x4,
+                            // side colors
+                            4 => Float32x4,
+                            5 => Float32x4,
+                            6 => Float32x4,
                         ],
                     },
                     wgpu::VertexBufferLayout {
@@ -185,7 +211,11 @@ impl Scene {
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
-            primitive: wgpu::PrimitiveState::default(),
+            primitive: wgpu::PrimitiveState {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode,
+                ..Default::default()
+            },
             //depth_stencil: None,
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: Texture::DEPTH_FORMAT,
@@ -1,9 +1,7 @@
-use std::collections::HashMap;
-
 use crate::render::{
     message::ColorMethodMessage,
     pipeline::{MomentVertex, ShapeVertex},
-    state::{ModelState, RenderState},
+    state::{ModelState, RenderState, UnfoldState},
 };
 use ultraviolet::{Vec3, Vec4};
 
@@ -18,84 +16,71 @@ impl PolyhedronPrimitive {
         Self { model, render }
     }
 
-    #[allow(dead_code)]
-    pub fn surface_area(&self, face_index: usize) -> f32 {
-        let positions: Vec<Vec3> = self.model.polyhedron.cycles[face_index]
-            .iter()
-            .map(|i| self.model.polyhedron.positions[i])
-            .collect();
-        let mut area = 0.0;
-        for i in 0..positions.len() / 3 {
-            let j = i * 3;
-            let a = (positions[j] - positions[j + 1]).mag();
-            let b = (positions[j + 1] - positions[j + 2]).mag();
-            let c = (positions[j + 2] - positions[j]).mag();
-            let s = (a + b + c) / 2.0;
-            area += (s * (s - a) * (s - b) * (s - c)).sqrt();
-        }
-        area
-    }
-
     /// All the vertices that will change moment to moment
     pub fn moment_vertices(&self) -> Vec<MomentVertex> {
         let polyhedron = &self.model.polyhedron;
-        let colors = &self.render.picker.palette.colors;
-
-        match self.render.method {
-            ColorMethodMessage::Vertex => todo!(),
-            ColorMethodMessage::Edge => todo!(),
-            ColorMethodMessage::Polygon => {
-                // Polygon side count -> color
-                let color_map: HashMap<usize, Vec4> =
-                    polyhedron.cycles.iter().fold(HashMap::new(), |mut acc, c| {
-                        if !acc.contains_key(&c.len()) {
-                            acc.insert(c.len(), colors[acc.len() % colors.len()].into());
-                        }
-                        acc
-                    });
+        let black = Vec4::new(0.0, 0.0, 0.0, 1.0);
+        // Colors of the corners and of the sides of every face
+        let colors: Vec<(Vec<Vec4>, Vec<Vec4>)> = match self.render.method {
+            ColorMethodMessage::Vertex => {
+                let vertex_colors = self.render.vertex_colors(polyhedron);
                 polyhedron
                     .cycles
                     .iter()
                     .map(|cycle| {
-                        let color = *color_map.get(&cycle.len()).unwrap();
-                        let positions: Vec<Vec3> =
-                            cycle.iter().map(|&c| polyhedron.positions[&c]).collect();
-
-                        match cycle.len() {
-                            3 => positions
-                                .iter()
-                                .map(|&position| MomentVertex::new(position, color))
-                                .collect(),
-                            4 => [0usize, 1, 2, 2, 3, 0]
-                                .iter()
-                                .map(|&i| positions[i])
-                                .map(|position| MomentVertex::new(position, color))
-                                .collect(),
-                            _ => {
-                                let centroid: Vec3 =
-                                    positions.iter().fold(Vec3::zero(), |a, &b| a + b)
-                                        / positions.len() as f32;
-                                (0..cycle.len())
-                                    .map(|i| {
-                                        vec![
-                                            positions[i],
-                                            centroid,
-                                            positions[(i + 1) % positions.len()],
-                                        ]
-                                        .into_iter()
-                                        .map(|position| MomentVertex::new(position, color))
-                                        .collect()
-                                    })
-                                    .collect::<Vec<Vec<MomentVertex>>>()
-                                    .concat()
-                            }
-                        }
+                        let corners = cycle.iter().map(|v| vertex_colors[v].into()).collect();
+                        (corners, vec![black; cycle.len()])
                     })
-                    .collect::<Vec<Vec<MomentVertex>>>()
-                    .concat()
+                    .collect()
             }
-            ColorMethodMessage::Face => todo!(),
-        }
+            ColorMethodMessage::Edge => {
+                let edge_colors = self.render.edge_colors(polyhedron);
+                let white = Vec4::one();
+                polyhedron
+                    .cycles
+                    .iter()
+                    .map(|cycle| {
+                        let n = cycle.len();
+                        let sides = (0..n)
+                            .map(|i| edge_colors[&(cycle[i], cycle[(i + 1) % n]).into()].into())
+                            .collect();
+                        (vec![white; n], sides)
+                    })
+                    .collect()
+            }
+            ColorMethodMessage::Polygon
+            | ColorMethodMessage::Face
+            | ColorMethodMessage::Orbit
+            | ColorMethodMessage::Proper
+            | ColorMethodMessage::Lineage => {
+                let face_colors = self.render.face_colors(polyhedron).unwrap_or_default();
+                polyhedron
+                    .cycles
+                    .iter()
+                    .zip(face_colors)
+                    .map(|(cycle, color)| {
+                        (vec![color.into(); cycle.len()], vec![black; cycle.len()])
+                    })
+                    .collect()
+            }
+        };
+
+        // Every face gets its own copy of its corners, so that they can come apart
+        let faces = match &self.render.unfold {
+            Some(UnfoldState {
+                net: Some((_, net)),
+                amount,
+                ..
+            }) if net.faces() == polyhedron.cycles.len() => polyhedron.unfolded(net, *amount),
+            _ => (0..polyhedron.cycles.len())
+                .map(|i| polyhedron.face_positions(i))
+                .collect(),
+        };
+        faces
+            .into_iter()
+            .zip(colors)
+            .flat_map(|(positions, (corners, sides))| triangles(&positions, &corners, &sides))
+            .collect()
     }
 
     pub fn shape_vertices(&self) -> Vec<ShapeVertex> {
@@ -135,3 +120,102 @@ impl PolyhedronPrimitive {
             .concat()
     }
 }
+
+/// Triangles covering a face, counterclockwise like it and matching `shape_vertices`.
+/// Each corner of a triangle carries the colors of all three of its sides,
+/// listed opposite the corners they face.
+fn triangles(positions: &[Vec3], corners: &[Vec4], sides: &[Vec4]) -> Vec<MomentVertex> {
+    let n = positions.len();
+    let vertex =
+        |position, color, opposite: [Vec4; 3]| MomentVertex::new(position, color, opposite);
+    // Diagonals and spokes aren't drawn, so their color doesn't matter
+    let hidden = Vec4::zero();
+    match n {
+        3 => (0..3)
+            .map(|i| vertex(positions[i], corners[i], [sides[1], sides[2], sides[0]]))
+            .collect(),
+        4 => [
+            (0, [sides[1], hidden, sides[0]]),
+            (1, [sides[1], hidden, sides[0]]),
+            (2, [sides[1], hidden, sides[0]]),
+            (2, [sides[3], hidden, sides[2]]),
+            (3, [sides[3], hidden, sides[2]]),
+            (0, [sides[3], hidden, sides[2]]),
+        ]
+        .into_iter()
+        .map(|(i, opposite)| vertex(positions[i], corners[i], opposite))
+        .collect(),
+        _ => {
+            let centroid = positions.iter().fold(Vec3::zero(), |a, &b| a + b) / n as f32;
+            let middle = corners.iter().fold(Vec4::zero(), |a, &b| a + b) / n as f32;
+            (0..n)
+                .flat_map(|i| {
+                    let j = (i + 1) % n;
+                    // Counterclockwise like the face, with the centroid second so the shapes line up
+                    let opposite = [hidden, sides[i], hidden];
+                    [
+                        vertex(positions[j], corners[j], opposite),
+                        vertex(centroid, middle, opposite),
+                        vertex(positions[i], corners[i], opposite),
+                    ]
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PolyhedronPrimitive;
+    use crate::{
+        bones::PolyGraph,
+        render::{
+            message::ColorMethodMessage,
+            state::{ModelState, RenderState},
+        },
+    };
+    use strum::IntoEnumIterator;
+    use test_case::test_case;
+
+    /// Every color method covers the same triangles the shapes do
+    #[test_case("P3"; "P3")]
+    #[test_case("tC"; "tC")]
+    #[test_case("gD"; "gD")]
+    fn color_methods(notation: &str) {
+        let model = ModelState {
+            polyhedron: notation.parse().unwrap(),
+            ..Default::default()
+        };
+        for method in ColorMethodMessage::iter() {
+            let render = RenderState {
+                method,
+                ..Default::default()
+            };
+            let primitive = PolyhedronPrimitive::new(model.clone(), render);
+            assert_eq!(
+                primitive.moment_vertices().len(),
+                primitive.shape_vertices().len()
+            );
+        }
+    }
+
+    /// Triangular prisms have one kind of vertex and two kinds of edge
+    #[test]
+    fn kinds() {
+        let prism = PolyGraph::prism(3);
+        let render = RenderState::default();
+        let colors = &render.picker.palette.colors;
+        let vertex_colors = render.vertex_colors(&prism);
+        assert!(vertex_colors
+            .values()
+            .all(|c| c.r == colors[0].r && c.g == colors[0].g));
+        let edge_colors = render.edge_colors(&prism);
+        let mut kinds = edge_colors
+            .values()
+            .map(|c| (c.r, c.g, c.b))
+            .collect::<Vec<_>>();
+        kinds.sort();
+        kinds.dedup();
+        assert_eq!(kinds.len(), 2);
+    }
+}
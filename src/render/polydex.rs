@@ -1,7 +1,54 @@
-use crate::{bones::PolyGraph, render::message::PolybladeMessage, Instant};
+use crate::{
+    bones::{Fingerprint, Notation, PolyGraph, Symmetry},
+    render::message::PolybladeMessage,
+    Instant,
+};
+use rustc_hash::FxHashMap as HashMap;
 use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
 
-pub type Polydex = Vec<Entry>;
+/// Known polyhedra, which can be found by their shape as well as their name
+#[derive(Debug, Clone, Default)]
+pub struct Polydex {
+    entries: Vec<Entry>,
+    /// Entry for each shape, built the first time a shape is looked up
+    fingerprints: OnceCell<HashMap<Fingerprint, usize>>,
+}
+
+impl Polydex {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        Self {
+            entries,
+            fingerprints: OnceCell::new(),
+        }
+    }
+
+    fn fingerprints(&self) -> &HashMap<Fingerprint, usize> {
+        self.fingerprints.get_or_init(|| {
+            let mut fingerprints = HashMap::default();
+            for (i, entry) in self.entries.iter().enumerate() {
+                // Some entries use notation we can't build, those can still be found by name
+                let structure = entry.conway.parse::<Notation>().map(|n| n.structure());
+                if let Ok(Ok(graph)) = structure {
+                    fingerprints.entry(graph.fingerprint().clone()).or_insert(i);
+                }
+            }
+            fingerprints
+        })
+    }
+
+    /// Entry with the same shape as `graph`, or its name. Shapes are only compared
+    /// once it has no operations queued, since each step of them changes the shape.
+    pub fn find(&self, graph: &PolyGraph) -> Option<&Entry> {
+        graph
+            .transactions
+            .is_empty()
+            .then(|| self.fingerprints().get(graph.fingerprint()))
+            .flatten()
+            .map(|&i| &self.entries[i])
+            .or_else(|| self.entries.iter().find(|entry| entry.conway == graph.name))
+    }
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Entry {
@@ -16,6 +63,8 @@ pub struct InfoBox {
     pub faces: usize,
     pub edges: usize,
     pub vertices: usize,
+    /// Only found once the polyhedron stops changing, since it takes a while on big ones
+    pub symmetry: Option<Symmetry>,
     name: Option<String>,
     bowers: Option<String>,
     wiki: Option<String>,
@@ -23,6 +72,7 @@ pub struct InfoBox {
 
 impl InfoBox {
     pub const UNKNOWN: &'static str = "Unknown";
+    pub const PENDING: &'static str = "…";
 
     pub fn name(&self) -> String {
         self.name.clone().unwrap_or(Self::UNKNOWN.to_string())
@@ -32,6 +82,28 @@ impl InfoBox {
         self.bowers.clone().unwrap_or(Self::UNKNOWN.to_string())
     }
 
+    pub fn symmetry(&self) -> String {
+        self.symmetry
+            .as_ref()
+            .map_or(Self::PENDING.to_string(), |symmetry| {
+                symmetry.group.to_string()
+            })
+    }
+
+    /// How many kinds of vertex, edge and face there are
+    pub fn orbits(&self) -> String {
+        self.symmetry
+            .as_ref()
+            .map_or(Self::PENDING.to_string(), |symmetry| {
+                format!(
+                    "{}, {}, {}",
+                    symmetry.vertex_orbits.len(),
+                    symmetry.edge_orbits.len(),
+                    symmetry.face_orbits.len()
+                )
+            })
+    }
+
     pub fn wiki_message(&self) -> PolybladeMessage {
         self.wiki
             .clone()
@@ -42,15 +114,61 @@ impl InfoBox {
 
 impl PolyGraph {
     pub fn polydex_entry(&self, polydex: &Polydex) -> InfoBox {
-        let entry = polydex.iter().find(|entry| entry.conway == self.name);
+        let entry = polydex.find(self);
         InfoBox {
             conway: self.name.clone(),
             faces: self.cycles.len(),
             edges: self.edges.len(),
             vertices: self.vertices.len(),
+            symmetry: self
+                .transactions
+                .is_empty()
+                .then(|| self.combinatorial_symmetry().clone()),
             name: entry.map(|e| e.name.clone()),
             bowers: entry.map(|e| e.bowers.clone()),
             wiki: entry.map(|e| e.wiki.clone()),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        bones::{Notation, PolyGraph},
+        render::state::load_polydex,
+    };
+
+    #[test]
+    fn find() {
+        let polydex = load_polydex().unwrap();
+        for (notation, name) in [
+            ("dD", "regular icosahedron"),
+            ("eT", "cuboctahedron"),
+            ("aaT", "cuboctahedron"),
+            ("jT", "cube"),
+            ("ddtC", "truncated cube"),
+        ] {
+            let graph: PolyGraph = notation.parse().unwrap();
+            assert_eq!(graph.polydex_entry(&polydex).name(), name);
+        }
+    }
+
+    /// Big polyhedra are left alone while they are still changing
+    #[test]
+    fn pending() {
+        let polydex = load_polydex().unwrap();
+        let notation: Notation = "tttI".parse().unwrap();
+        let animated = notation.animated();
+        let info = animated.polydex_entry(&polydex);
+        assert!(info.symmetry.is_none());
+        assert!(animated.symmetry.get().is_none());
+        assert!(animated.fingerprint.get().is_none());
+
+        // And once they settle, their symmetry is found in reasonable time
+        let settled = notation.build().unwrap();
+        let start = std::time::Instant::now();
+        let info = settled.polydex_entry(&polydex);
+        assert_eq!(info.symmetry(), "Ih");
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    }
+}
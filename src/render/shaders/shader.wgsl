@@ -10,6 +10,9 @@ struct Output {
     @location(1) v_color: vec4<f32>,
     @location(2) v_barycentric: vec4<f32>,
     @location(3) v_sides: vec4<f32>,
+    @location(4) v_side_x: vec4<f32>,
+    @location(5) v_side_y: vec4<f32>,
+    @location(6) v_side_z: vec4<f32>,
 };
 
 @vertex
@@ -18,6 +21,9 @@ fn vs_main(
     @location(1) color: vec4<f32>,
     @location(2) barycentric: vec4<f32>,
     @location(3) sides: vec4<f32>,
+    @location(4) side_x: vec4<f32>,
+    @location(5) side_y: vec4<f32>,
+    @location(6) side_z: vec4<f32>,
 ) -> Output {
     var output: Output;
     let m_position: vec4<f32> = uniforms.model_mat * position;
@@ -26,6 +32,9 @@ fn vs_main(
     output.v_color = color;
     output.v_barycentric = barycentric;
     output.v_sides = sides;
+    output.v_side_x = side_x;
+    output.v_side_y = side_y;
+    output.v_side_z = side_z;
 
     output.position = uniforms.view_project_mat * m_position;
     return output;
@@ -38,10 +47,15 @@ struct FragUniforms {
 };
 @binding(1) @group(0) var<uniform> frag_uniforms : FragUniforms;
 
-fn edge(v_barycentric: vec3<f32>, v_sides: vec3<f32>) -> bool {
+// Zero in each component near the side opposite that corner
+fn near(v_barycentric: vec3<f32>, v_sides: vec3<f32>) -> vec3<f32> {
     let face: vec3<f32> = v_barycentric * v_sides;
     let r: vec3<f32> = fwidthFine(face) * frag_uniforms.line_thickness;
-    let f: vec3<f32> = step(r, face);
+    return step(r, face);
+}
+
+fn edge(v_barycentric: vec3<f32>, v_sides: vec3<f32>) -> bool {
+    let f: vec3<f32> = near(v_barycentric, v_sides);
     return min(min(f.x, f.y), f.z) == 0.0;
 }
 
@@ -58,10 +72,18 @@ fn fs_main(
     @location(1) v_color: vec4<f32>,
     @location(2) v_barycentric: vec4<f32>,
     @location(3) v_sides: vec4<f32>,
+    @location(4) v_side_x: vec4<f32>,
+    @location(5) v_side_y: vec4<f32>,
+    @location(6) v_side_z: vec4<f32>,
 ) -> @location(0) vec4<f32> {
     if frag_uniforms.line_mode != 0.0 {
-        if edge(v_barycentric.xyz, v_sides.xyz) {
-            return vec4(0.0, 0.0, 0.0, 1.0);
+        let f: vec3<f32> = near(v_barycentric.xyz, v_sides.xyz);
+        if f.x == 0.0 {
+            return v_side_x;
+        } else if f.y == 0.0 {
+            return v_side_y;
+        } else if f.z == 0.0 {
+            return v_side_z;
         } else {
             return v_color;
         }
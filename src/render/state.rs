@@ -1,8 +1,9 @@
 use crate::{
-    bones::PolyGraph,
+    bones::{Edge, HalfEdges, Net, PolyGraph, Provenance, Transaction, VertexId},
     render::{
         camera::Camera,
-        message::ColorMethodMessage,
+        color::RGBA,
+        message::{ColorMethodMessage, LayoutMessage},
         palette::Palette,
         polydex::{Entry, InfoBox, Polydex},
     },
@@ -10,14 +11,25 @@ use crate::{
 };
 
 use iced::{time::Duration, Color};
+use rustc_hash::FxHashMap as HashMap;
 use std::{f32::consts::PI, io::Read as _};
-use ultraviolet::Mat4;
+use ultraviolet::{Mat4, Vec3};
 
 pub struct AppState {
     pub model: ModelState,
     pub render: RenderState,
     pub polydex: Polydex,
     pub info: InfoBox,
+    /// Conway notation being typed
+    pub notation: String,
+    /// Why the last notation, import or operation failed
+    pub error: Option<String>,
+    /// Why the faces aren't colored the way they were asked to be
+    pub warning: Option<String>,
+    /// Polyhedra to undo and redo back to
+    pub history: History,
+    /// Whether the info box shows measurements of the polyhedron
+    pub measuring: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -32,8 +44,31 @@ pub struct RenderState {
     pub schlegel: bool,
     pub line_thickness: f32,
     pub method: ColorMethodMessage,
+    pub layout: LayoutMessage,
     pub picker: ColorPickerState,
     pub background_color: Color,
+    /// Faces opening out into a net, when that is being shown
+    pub unfold: Option<UnfoldState>,
+    /// Symmetry orbit of each face, numbered the same way every time the structure is the same
+    pub face_orbits: Vec<usize>,
+    /// Colors asked for, and the color of each face so that neighbours never match
+    pub proper_coloring: Option<(usize, Vec<usize>)>,
+    /// Whether the structure has changed since the proper coloring was found.
+    /// It is still shown until the polyhedron settles and a new one is found.
+    pub proper_coloring_stale: bool,
+}
+
+/// Seconds taken to unfold the net from closed to flat
+const UNFOLD_SECONDS: f32 = 4.0;
+
+#[derive(Debug, Clone, Default)]
+pub struct UnfoldState {
+    /// Hinges between faces, and the polyhedron they were found for
+    pub net: Option<(String, Net)>,
+    /// From closed at zero to flat at one
+    pub amount: f32,
+    /// Whether the net opens by itself, rather than being held by the slider
+    pub opening: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -57,12 +92,146 @@ impl Default for RenderState {
             schlegel: false,
             line_thickness: 2.0,
             method: ColorMethodMessage::Polygon,
+            layout: LayoutMessage::Spring,
             picker: ColorPickerState::default(),
             background_color: Color::WHITE,
+            unfold: None,
+            face_orbits: Vec::new(),
+            proper_coloring: None,
+            proper_coloring_stale: false,
         }
     }
 }
 
+impl RenderState {
+    /// Color of each face, when the color method gives every face a single color
+    pub fn face_colors(&self, polyhedron: &PolyGraph) -> Option<Vec<RGBA>> {
+        match self.method {
+            ColorMethodMessage::Polygon => Some(self.polygon_colors(polyhedron)),
+            ColorMethodMessage::Face => Some(self.face_index_colors(polyhedron)),
+            ColorMethodMessage::Orbit => Some(self.orbit_colors(polyhedron)),
+            ColorMethodMessage::Proper => Some(self.proper_colors(polyhedron)),
+            ColorMethodMessage::Lineage => Some(self.lineage_colors(polyhedron)),
+            _ => None,
+        }
+    }
+
+    /// Color of each face, by its symmetry orbit
+    pub fn orbit_colors(&self, polyhedron: &PolyGraph) -> Vec<RGBA> {
+        // Orbits are found after the structure changes, so fall back on the polygons until then
+        if self.face_orbits.len() != polyhedron.cycles.len() {
+            return self.polygon_colors(polyhedron);
+        }
+        self.face_orbits
+            .iter()
+            .map(|&orbit| self.ranked_color(orbit))
+            .collect()
+    }
+
+    /// Color of each face, from a proper coloring over the first colors of the palette
+    pub fn proper_colors(&self, polyhedron: &PolyGraph) -> Vec<RGBA> {
+        match &self.proper_coloring {
+            Some((_, coloring)) if coloring.len() == polyhedron.cycles.len() => coloring
+                .iter()
+                .map(|&color| self.ranked_color(color))
+                .collect(),
+            // Only searched for while this method is shown and the polyhedron is settled,
+            // so there is none until then, or the last one is for other faces
+            _ => self.polygon_colors(polyhedron),
+        }
+    }
+
+    /// Color of each face, by the lineage it descends from
+    pub fn lineage_colors(&self, polyhedron: &PolyGraph) -> Vec<RGBA> {
+        polyhedron
+            .face_lineages()
+            .into_iter()
+            .map(|lineage| self.ranked_color(lineage))
+            .collect()
+    }
+
+    /// Color of each face, going through the palette in order
+    pub fn face_index_colors(&self, polyhedron: &PolyGraph) -> Vec<RGBA> {
+        let colors = &self.picker.palette.colors;
+        (0..polyhedron.cycles.len())
+            .map(|i| colors[i % colors.len()])
+            .collect()
+    }
+
+    /// Color of each vertex, by its degree
+    pub fn vertex_colors(&self, polyhedron: &PolyGraph) -> HashMap<VertexId, RGBA> {
+        let degrees = polyhedron
+            .vertices
+            .iter()
+            .map(|&v| (v, polyhedron.connections(v).len()))
+            .collect::<HashMap<_, _>>();
+        let ranks = ranks(degrees.values().copied());
+        degrees
+            .into_iter()
+            .map(|(v, degree)| (v, self.ranked_color(ranks[&degree])))
+            .collect()
+    }
+
+    /// Color of each edge, by the sizes of the two faces either side of it
+    pub fn edge_colors(&self, polyhedron: &PolyGraph) -> HashMap<Edge, RGBA> {
+        let mut sides: HashMap<Edge, (usize, usize)> = HashMap::default();
+        for cycle in polyhedron.cycles.iter() {
+            for e in cycle.edges() {
+                let (small, large) = sides.entry(e).or_insert((usize::MAX, 0));
+                *small = (*small).min(cycle.len());
+                *large = (*large).max(cycle.len());
+            }
+        }
+        let ranks = ranks(sides.values().copied());
+        sides
+            .into_iter()
+            .map(|(e, pair)| (e, self.ranked_color(ranks[&pair])))
+            .collect()
+    }
+
+    fn ranked_color(&self, rank: usize) -> RGBA {
+        let colors = &self.picker.palette.colors;
+        colors[rank % colors.len()]
+    }
+
+    /// Color of each face, by its number of sides
+    pub fn polygon_colors(&self, polyhedron: &PolyGraph) -> Vec<RGBA> {
+        let colors = &self.picker.palette.colors;
+        // Polygon side count -> color
+        let mut color_map: HashMap<usize, RGBA> = HashMap::default();
+        polyhedron
+            .cycles
+            .iter()
+            .map(|cycle| {
+                let next = colors[color_map.len() % colors.len()];
+                *color_map.entry(cycle.len()).or_insert(next)
+            })
+            .collect()
+    }
+}
+
+/// Position of each distinct value once they are sorted,
+/// so the same kinds of element get the same colors whatever order they come in
+fn ranks<T: Ord + std::hash::Hash + Copy>(values: impl Iterator<Item = T>) -> HashMap<T, usize> {
+    let mut distinct = values.collect::<Vec<_>>();
+    distinct.sort();
+    distinct.dedup();
+    distinct
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| (x, i))
+        .collect()
+}
+
+impl ColorPickerState {
+    /// Paint with a different palette, using no more of its colors than it has
+    pub fn switch(&mut self, palette: Palette) {
+        self.colors = self.colors.min(palette.colors.len() as i16);
+        self.palette = palette;
+        self.color_index = None;
+    }
+}
+
 impl Default for ColorPickerState {
     fn default() -> Self {
         Self {
@@ -89,22 +258,121 @@ impl Default for ModelState {
     }
 }
 
+/// Most changes that can be undone
+const HISTORY_LENGTH: usize = 64;
+
+/// What the history keeps of a polyhedron: its structure, layout and name,
+/// and whatever it was still animating, which carries on when it comes back.
+/// Distances and springs are found again.
+#[derive(Debug, Clone)]
+struct Saved {
+    name: String,
+    half_edges: HalfEdges,
+    positions: HashMap<VertexId, Vec3>,
+    provenance: Provenance,
+    transactions: Vec<Transaction>,
+}
+
+impl From<&PolyGraph> for Saved {
+    fn from(polyhedron: &PolyGraph) -> Self {
+        Self {
+            name: polyhedron.name.clone(),
+            half_edges: polyhedron.half_edges.clone(),
+            positions: polyhedron.positions.clone(),
+            provenance: polyhedron.provenance.clone(),
+            transactions: polyhedron.transactions.clone(),
+        }
+    }
+}
+
+impl Saved {
+    fn restore(self) -> PolyGraph {
+        let mut polyhedron = PolyGraph {
+            name: self.name,
+            edge_length: 1.0,
+            provenance: self.provenance,
+            transactions: self.transactions,
+            ..Default::default()
+        };
+        polyhedron.replace(self.half_edges);
+        polyhedron.positions = self.positions;
+        polyhedron.springs();
+        polyhedron
+    }
+}
+
+/// Polyhedra from before the latest changes, and from before those changes were undone,
+/// each with the number it was recorded under
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: Vec<(usize, Saved)>,
+    redo: Vec<(usize, Saved)>,
+    records: usize,
+}
+
+impl History {
+    /// Remember a polyhedron that is about to change, forgetting anything undone.
+    /// Returns the number of the record, for operations to mark their end with.
+    pub fn record(&mut self, polyhedron: &PolyGraph) -> usize {
+        if self.undo.len() == HISTORY_LENGTH {
+            self.undo.remove(0);
+        }
+        let record = self.records;
+        self.records += 1;
+        self.undo.push((record, polyhedron.into()));
+        self.redo.clear();
+        record
+    }
+
+    /// Forget the records of operations that were rolled back, since they never happened
+    pub fn rolled_back(&mut self, records: &[usize]) {
+        self.undo.retain(|(record, _)| !records.contains(record));
+    }
+
+    /// Polyhedron from before the last change, which moves there from the current one
+    pub fn undo(&mut self, current: &PolyGraph) -> Option<PolyGraph> {
+        Self::step(&mut self.undo, &mut self.redo, current)
+    }
+
+    /// Polyhedron from before the last undo, which moves there from the current one
+    pub fn redo(&mut self, current: &PolyGraph) -> Option<PolyGraph> {
+        Self::step(&mut self.redo, &mut self.undo, current)
+    }
+
+    fn step(
+        from: &mut Vec<(usize, Saved)>,
+        to: &mut Vec<(usize, Saved)>,
+        current: &PolyGraph,
+    ) -> Option<PolyGraph> {
+        let (record, saved) = from.pop()?;
+        let mut polyhedron = saved.restore();
+        to.push((record, current.into()));
+        polyhedron.morph_from(current);
+        Some(polyhedron)
+    }
+}
+
 pub fn load_polydex() -> Result<Polydex, Box<dyn std::error::Error>> {
     let mut polydex = std::fs::File::open("assets/polydex.ron")?;
     let mut polydex_str = String::new();
     polydex.read_to_string(&mut polydex_str)?;
     let polydex: Vec<Entry> = ron::from_str(&polydex_str).map_err(|_| "Ron parsing error")?;
-    Ok(polydex)
+    Ok(Polydex::new(polydex))
 }
 
 impl Default for AppState {
     fn default() -> Self {
-        let info = PolyGraph::default().polydex_entry(&vec![]);
+        let info = PolyGraph::default().polydex_entry(&Polydex::default());
         Self {
             model: ModelState::default(),
             render: RenderState::default(),
             polydex: load_polydex().unwrap_or_default(),
             info,
+            notation: String::new(),
+            error: None,
+            warning: None,
+            history: History::default(),
+            measuring: false,
         }
     }
 }
@@ -122,9 +390,31 @@ impl AppState {
             frame_difference
         };
 
-        self.model.polyhedron.update(self.render.speed, second);
+        if let Err(error) =
+            self.model
+                .polyhedron
+                .update(&self.render.layout, self.render.speed, second)
+        {
+            log::warn!("failed to apply an operation: {error}");
+            self.error = Some(error.to_string());
+            self.history
+                .rolled_back(&std::mem::take(&mut self.model.polyhedron.rolled_back));
+        }
         self.render.frame = time;
 
+        if let Some(unfold) = self.render.unfold.as_mut() {
+            // Hinges have to be found again whenever the structure changes
+            let polyhedron = &self.model.polyhedron;
+            if unfold.net.as_ref().is_none_or(|(name, net)| {
+                *name != polyhedron.name || net.faces() != polyhedron.cycles.len()
+            }) {
+                unfold.net = Some((polyhedron.name.clone(), Net::new(&polyhedron.mesh())));
+            }
+            if unfold.opening {
+                unfold.amount = (unfold.amount + second / UNFOLD_SECONDS).min(1.0);
+            }
+        }
+
         let time = if self.render.rotating {
             time.duration_since(self.render.start)
         } else {
@@ -142,3 +432,70 @@ impl AppState {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::History;
+    use crate::{
+        bones::{PolyGraph, Transaction},
+        render::message::ConwayMessage,
+    };
+
+    #[test]
+    fn history() {
+        let mut history = History::default();
+        let cube = PolyGraph::prism(4);
+        let truncated: PolyGraph = "tC".parse().unwrap();
+        assert!(history.undo(&cube).is_none());
+
+        history.record(&cube);
+        let undone = history.undo(&truncated).unwrap();
+        assert_eq!(undone.name, cube.name);
+        // Every vertex of the cube starts out on one of the truncated cube's
+        for v in undone.vertices.iter() {
+            assert!(truncated
+                .positions
+                .values()
+                .any(|p| *p == undone.positions[v]));
+        }
+        assert!(history.undo(&undone).is_none());
+
+        let redone = history.redo(&undone).unwrap();
+        assert_eq!(redone.name, "tC");
+        assert!(history.redo(&redone).is_none());
+
+        // Changing anything forgets what was undone
+        history.undo(&redone).unwrap();
+        history.record(&cube);
+        assert!(history.redo(&cube).is_none());
+
+        // Restored from just the structure and layout
+        let undone = history.undo(&truncated).unwrap();
+        assert!(undone.transactions.is_empty());
+        assert_eq!(undone.springs, cube.springs);
+
+        // Operations still in progress carry on from where they were
+        let mut expanding = cube.clone();
+        expanding.transactions = vec![Transaction::Conway(ConwayMessage::Expand)];
+        history.record(&expanding);
+        let undone = history.undo(&truncated).unwrap();
+        assert_eq!(undone.name, cube.name);
+        assert!(matches!(
+            undone.transactions.as_slice(),
+            [Transaction::Conway(ConwayMessage::Expand)]
+        ));
+
+        // Changes that were rolled back leave nothing to undo
+        let record = history.record(&cube);
+        history.rolled_back(&[record]);
+        assert!(history.undo(&cube).is_none());
+
+        // But the changes before them still can be
+        history.record(&cube);
+        let record = history.record(&truncated);
+        history.rolled_back(&[record]);
+        let undone = history.undo(&truncated).unwrap();
+        assert_eq!(undone.name, cube.name);
+        assert!(history.undo(&undone).is_none());
+    }
+}